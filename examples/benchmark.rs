@@ -0,0 +1,14 @@
+use ciuc_planet::ciuc::simulation::{BenchGrid, BenchPolicy, run_benchmark};
+
+// Compares the statistic state with the safe state on the default grid.
+// Run with `cargo run --release --example benchmark [-- --csv]`
+fn main() -> Result<(), String> {
+    let csv = std::env::args().any(|a| a == "--csv");
+    let report = run_benchmark(&BenchPolicy::statistic_vs_safe(), &BenchGrid::default())?;
+    if csv {
+        print!("{}", report.to_csv());
+    } else {
+        print!("{}", report.to_table());
+    }
+    Ok(())
+}
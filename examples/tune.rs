@@ -0,0 +1,31 @@
+use ciuc_planet::CiucConfig;
+use ciuc_planet::ciuc::simulation::{BenchGrid, SearchMethod, Tuner};
+
+// Searches the safe-cell thresholds delivering the most carbon while surviving 99% of the runs,
+// then prints the recommended configuration as JSON.
+// Run with `cargo run --release --example tune`
+fn main() -> Result<(), String> {
+    let environments = BenchGrid {
+        seeds: 5,
+        ..Default::default()
+    };
+    let method = SearchMethod::Evolutionary {
+        population: 8,
+        generations: 4,
+    };
+    let result = Tuner::new(CiucConfig::default(), environments, method).run()?;
+
+    eprintln!(
+        "baseline: survival {:.1}%, carbon {:.1}",
+        result.baseline.survival_rate * 100.0,
+        result.baseline.carbon_delivered
+    );
+    eprintln!(
+        "tuned ({} configurations tried): survival {:.1}%, carbon {:.1}",
+        result.evaluated,
+        result.evaluation.survival_rate * 100.0,
+        result.evaluation.carbon_delivered
+    );
+    println!("{}", result.config_json()?);
+    Ok(())
+}
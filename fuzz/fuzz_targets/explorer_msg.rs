@@ -0,0 +1,12 @@
+#![no_main]
+
+use ciuc_planet::ciuc::testing::{decode_simulation, fuzz_events};
+use libfuzzer_sys::fuzz_target;
+
+// Drives the planet with the explorer requests, sunrays and asteroids decoded from the input.
+// Run with `cargo +nightly fuzz run explorer_msg`
+fuzz_target!(|data: &[u8]| {
+    if let Err(e) = fuzz_events(data) {
+        panic!("{e}\nsimulation: {:?}", decode_simulation(data));
+    }
+});
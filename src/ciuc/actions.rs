@@ -1,5 +1,7 @@
 use crate::CiucAI;
-use crate::ciuc::AIState;
+use crate::ciuc::carbon::SafeCellsStage;
+use crate::ciuc::decision::CarbonDecision;
+use crate::ciuc::policy::PolicyFeedback;
 use common_game::components::planet::PlanetState;
 use common_game::components::resource::{Carbon, Generator};
 use common_game::components::rocket::Rocket;
@@ -10,7 +12,11 @@ impl CiucAI {
     pub(crate) fn build_rocket(&self, planet_state: &mut PlanetState) -> Result<(), String> {
         match planet_state.full_cell() {
             None => Err("Didn't find any charged cell, impossible to build a rocket".to_string()),
-            Some((_cell, i)) => planet_state.build_rocket(i),
+            Some((_cell, i)) => {
+                planet_state.build_rocket(i)?;
+                self.metrics_handle().record(|m| m.rockets_built += 1);
+                Ok(())
+            }
         }
     }
 
@@ -30,24 +36,73 @@ impl CiucAI {
             None => Ok(()),
             Some(_) => {
                 // All cells are full of charge, discard the sunray
+                self.metrics_handle().record(|m| m.sunrays_wasted += 1);
                 Err("All cells are full of charge".to_string())
             }
         }
     }
 
-    ///Function for generating carbon
+    ///Function for generating carbon (or taking it from the stock), the decision is recorded in the decision history
     pub(crate) fn generate_carbon(
-        &self,
+        &mut self,
         planet_state: &mut PlanetState,
         generator: &Generator,
+        explorer_id: u32,
     ) -> Result<Carbon, String> {
+        let now = self.now();
+        let observation = self.observation(planet_state, now); // Before the carbon is made
 
-        let safe_cells = self.current_safe_cells(planet_state);
+        // Carbon already in stock costs no energy, so it's given away first, without asking the policy
+        let stocked = self.take_stocked_carbon();
+        let from_stock = stocked.is_some();
+        let (safe_cells, res) = match stocked {
+            Some(carbon) => (self.safe_cells_breakdown(planet_state, now), Ok(carbon)),
+            None => {
+                let safe_cells = self.decide_safe_cells(planet_state, now); // The policy may explore
+                let res = self.generate_carbon_if_have_n_safe_cells(
+                    planet_state,
+                    generator,
+                    safe_cells.value,
+                );
+                (safe_cells, res)
+            }
+        };
 
-        self.generate_carbon_if_have_n_safe_cells(
-            planet_state,
-            generator,
-            safe_cells,
-        )
+        self.record_decision(CarbonDecision {
+            explorer_id,
+            state: observation.state,
+            charged_cells: observation.charged_cells,
+            estimate_sunray_ms: observation.estimate_sunray_ms,
+            estimate_asteroid_ms: observation.estimate_asteroid_ms,
+            since_last_sunray_ms: observation.since_last_sunray_ms,
+            since_last_asteroid_ms: observation.since_last_asteroid_ms,
+            until_next_sunray_ms: observation.until_next_sunray_ms,
+            until_next_asteroid_ms: observation.until_next_asteroid_ms,
+            sunray_adjustment: (-safe_cells.applied(SafeCellsStage::SunrayImminent)) as u32,
+            asteroid_far: safe_cells.asteroid_far,
+            margin: safe_cells.applied(SafeCellsStage::Margin) as i32,
+            safe_cells: safe_cells.value,
+            adjustments: safe_cells.adjustments,
+            granted: res.is_ok(),
+            from_stock,
+            refusal: res.as_ref().err().cloned(),
+        });
+        self.metrics_handle().record(|m| {
+            let counter = if res.is_ok() {
+                m.carbon_generated.entry(explorer_id)
+            } else {
+                m.carbon_refused.entry(explorer_id)
+            };
+            *counter.or_insert(0) += 1;
+        });
+        // The stocked carbon didn't depend on the policy
+        if !from_stock {
+            self.policy_feedback(if res.is_ok() {
+                PolicyFeedback::CarbonGranted
+            } else {
+                PolicyFeedback::CarbonRefused
+            });
+        }
+        res
     }
 }
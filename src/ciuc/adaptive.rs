@@ -0,0 +1,92 @@
+use crate::CiucAI;
+use crate::ciuc::carbon::{SafeCells, SafeCellsStage};
+use crate::ciuc::logging::LogCategory;
+use common_game::components::planet::PlanetState;
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+use serde::{Deserialize, Serialize};
+
+/// Online adaptation of the safe-cell margin: one more cell after a near-death,
+/// one less after 'relax_after_ms' without any, always within [min_margin, max_margin]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveMarginConfig {
+    /// Lowest margin, negative to preserve fewer cells than the thresholds
+    pub min_margin: i32,
+    /// Highest margin
+    pub max_margin: i32,
+    /// Time without near-deaths after which the margin is relaxed by one cell
+    pub relax_after_ms: i64,
+}
+
+impl Default for AdaptiveMarginConfig {
+    fn default() -> Self {
+        AdaptiveMarginConfig {
+            min_margin: -1,
+            max_margin: 2,
+            relax_after_ms: 120_000,
+        }
+    }
+}
+
+impl CiucAI {
+    ///Function that adds the margin to the safe cells, without going below zero or above the cells of the planet
+    pub(crate) fn apply_margin(&self, safe_cells: SafeCells) -> SafeCells {
+        safe_cells.adjust(SafeCellsStage::Margin, self.safe_cells_margin() as i64)
+    }
+
+    ///Function for tightening the margin if the asteroid left the planet without rocket and without charge:
+    ///the next asteroid would have destroyed it
+    pub(crate) fn adapt_margin_after_asteroid(&mut self, planet_state: &PlanetState) {
+        let Some(adaptive) = self.config().adaptive_margin else {
+            return;
+        };
+        let defenseless =
+            !planet_state.has_rocket() && !planet_state.cells_iter().any(|c| c.is_charged());
+        if defenseless {
+            let margin = (self.safe_cells_margin() + 1).min(adaptive.max_margin);
+            self.set_margin(planet_state.id(), margin, "after a near-death");
+        }
+    }
+
+    ///Function for relaxing the margin after a long period without near-deaths
+    pub(crate) fn relax_margin_if_safe(&mut self, id: u32) {
+        let Some(adaptive) = self.config().adaptive_margin else {
+            return;
+        };
+        let now = self.now();
+        let Some(changed) = self.margin_changed_ms() else {
+            // The safe period starts with the first event
+            self.set_margin_changed_ms(now);
+            return;
+        };
+        if now - changed >= adaptive.relax_after_ms {
+            let margin = (self.safe_cells_margin() - 1).max(adaptive.min_margin);
+            self.set_margin(id, margin, "after a long safe period");
+        }
+    }
+
+    ///Function for changing the margin, every change is logged
+    fn set_margin(&mut self, id: u32, margin: i32, reason: &str) {
+        let previous = self.safe_cells_margin();
+        self.set_margin_changed_ms(self.now());
+        if margin == previous {
+            return; // Already at the bound
+        }
+        self.set_safe_cells_margin(margin);
+        self.log(
+            LogCategory::SafeCells,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Info,
+            [(
+                "message",
+                format!(
+                    "Changed the safe-cell margin from {} to {} {}",
+                    previous, margin, reason
+                ),
+            )],
+        );
+    }
+}
@@ -1,10 +1,10 @@
 use crate::CiucAI;
-use crate::ciuc::esteem::now_ms;
 use common_game::components::planet::PlanetState;
 use common_game::components::resource::{Carbon, Generator};
 use common_game::logging::Participant;
 use common_game::logging::{ActorType, Channel, EventType};
-use crate::ciuc::AIState;
+use crate::ciuc::logging::LogCategory;
+use serde::{Deserialize, Serialize};
 
 mod safe {
     /// Number of energy cells to preserve in safe state
@@ -24,67 +24,169 @@ mod statistic {
     pub(crate) const ASTEROID_FAR_THRESHOLD: f64 = 0.5;
 }
 
+/// Thresholds deciding how many charged cells are preserved, the defaults are the hand-tuned values
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafeCellsConfig {
+    /// Cells preserved in 'SafeState'
+    pub safe_cells: u32,
+    /// Cells preserved in 'StatisticState' when the asteroid is far
+    pub safe_cells_far_asteroid: u32,
+    /// Cells preserved in 'StatisticState' when the asteroid is near
+    pub safe_cells_near_asteroid: u32,
+    /// Fraction of the estimated sunray interval after which a sunray is considered imminent
+    pub sunray_imminent_threshold: f64,
+    /// Fraction of the estimated asteroid interval under which the asteroid is considered far
+    pub asteroid_far_threshold: f64,
+    /// Cells added (removed, if negative) in 'StatisticState' for each explorer on the planet beyond the first
+    pub cells_per_extra_explorer: i32,
+}
+
+impl Default for SafeCellsConfig {
+    fn default() -> Self {
+        SafeCellsConfig {
+            safe_cells: safe::SAFE_CELLS,
+            safe_cells_far_asteroid: statistic::SAFE_CELLS_FAR_ASTEROID,
+            safe_cells_near_asteroid: statistic::SAFE_CELLS_NEAR_ASTEROID,
+            sunray_imminent_threshold: statistic::SUNRAY_IMMINENT_THRESHOLD,
+            asteroid_far_threshold: statistic::ASTEROID_FAR_THRESHOLD,
+            cells_per_extra_explorer: 0,
+        }
+    }
+}
+
+/// Stage of the safe-cell pipeline, in the order in which they are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafeCellsStage {
+    Base,           // Threshold of the state (of a far asteroid in 'StatisticState')
+    AsteroidNear,   // Extra cells because the asteroid may come soon
+    SunrayImminent, // Cells released because a sunray will recharge them
+    ExplorerDemand, // Change for the explorers on the planet
+    Margin,         // Online adaptation after near-deaths
+}
+
+/// Change of the safe cells made by one stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeCellsAdjustment {
+    pub stage: SafeCellsStage,
+    /// Change asked by the stage (the whole value for 'Base')
+    pub requested: i64,
+    /// Change left after keeping the value within [0, cell_count]
+    pub applied: i64,
+    /// Safe cells after the stage
+    pub value: u32,
+}
+
+/// Number of safe cells together with the adjustments that produced it.
+/// Every stage is bounded to [0, cell_count], so no threshold can underflow or exceed the planet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafeCells {
+    pub value: u32,
+    pub cell_count: u32,
+    pub asteroid_far: Option<bool>, // None in 'SafeState', where the asteroid is not considered
+    pub adjustments: Vec<SafeCellsAdjustment>,
+}
+
+impl SafeCells {
+    ///Function that starts the pipeline from the base threshold
+    pub fn new(base: u32, cell_count: u32) -> Self {
+        SafeCells {
+            value: 0,
+            cell_count,
+            asteroid_far: None,
+            adjustments: Vec::new(),
+        }
+        .stage(SafeCellsStage::Base, base as i64)
+    }
+
+    ///Function that applies a change to the safe cells, a zero change is not recorded
+    pub fn adjust(self, stage: SafeCellsStage, delta: i64) -> Self {
+        if delta == 0 {
+            return self;
+        }
+        self.stage(stage, delta)
+    }
+
+    fn stage(mut self, stage: SafeCellsStage, requested: i64) -> Self {
+        let value = (self.value as i64)
+            .saturating_add(requested)
+            .clamp(0, self.cell_count as i64) as u32;
+        self.adjustments.push(SafeCellsAdjustment {
+            stage,
+            requested,
+            applied: value as i64 - self.value as i64,
+            value,
+        });
+        self.value = value;
+        self
+    }
+
+    ///Function that returns the change applied by a stage
+    pub fn applied(&self, stage: SafeCellsStage) -> i64 {
+        self.adjustments
+            .iter()
+            .filter(|a| a.stage == stage)
+            .map(|a| a.applied)
+            .sum()
+    }
+}
+
 impl CiucAI {
+    ///Function for counting an explorer arrived on the planet, for the 'ExplorerDemand' stage
+    pub(crate) fn explorer_arrived(&mut self) {
+        self.set_number_explorers(self.number_explorers() + 1);
+    }
+
+    ///Function for counting an explorer departed from the planet, for the 'ExplorerDemand' stage
+    pub(crate) fn explorer_departed(&mut self) {
+        self.set_number_explorers(self.number_explorers().saturating_sub(1));
+    }
+
     ///Function that returns the exact number of safe cells
     pub(crate) fn current_safe_cells(&self, planet_state: &mut PlanetState,) -> u32 {
-        match self.state() {
-            AIState::SafeState => safe::SAFE_CELLS,
-
-            AIState::StatisticState => {
-                let now = now_ms();
-
-                let time_passed_last_sunray =
-                    now - self.last_time_sunray();
-
-                let time_passed_last_asteroid =
-                    now - self.last_time_asteroid();
-
-                let mut remove_safe_cell_cause_sunray = 0;
-
-                // If a sunray is expected soon, we can generate faster (the safe cell will return immediately)
-                if (time_passed_last_sunray as f64)
-                    > (statistic::SUNRAY_IMMINENT_THRESHOLD
-                    * self.estimate_sunray_ms())
-                {
-                    CiucAI::log_event(
-                        Some(Participant::new(ActorType::User, planet_state.id())),
-                        None,
-                        EventType::InternalPlanetAction,
-                        Channel::Debug,
-                        [(
-                            "message",
-                            "I estimate that a sunray may arrive, so I reduce the cells to be preserved by one.",
-                        )],
-                    );
-                    remove_safe_cell_cause_sunray = 1;
-                }
+        self.safe_cells_breakdown(planet_state, self.now()).value
+    }
 
-                // If the asteroid is far away (less than half the estimated time has passed)
-                if (time_passed_last_asteroid as f64)
-                    < (statistic::ASTEROID_FAR_THRESHOLD
-                    * self.estimate_asteroid_ms())
-                {
-                    let safe_cell = statistic::SAFE_CELLS_FAR_ASTEROID - remove_safe_cell_cause_sunray;     // If a sunray is expected, use one less cell as it will return immediately
-                    CiucAI::log_event(
-                        Some(Participant::new(ActorType::User, planet_state.id())),
-                        None,
-                        EventType::InternalPlanetAction,
-                        Channel::Debug,
-                        [(
-                            "message",
-                            format!(
-                                "the asteroid is far away so I reserve {} cells for my survival.",
-                                safe_cell
-                            ),
-                        )],);
-                    safe_cell
-                } else {
-                    let safe_cell = statistic::SAFE_CELLS_NEAR_ASTEROID - remove_safe_cell_cause_sunray;    // If a sunray is expected, generate with one less cell as it will return immediately
-                    // Generate less quickly, keeping two SAFE cells (or one if sunray expected)
-                    safe_cell
-                }
-            }
+    ///Function that returns the number of safe cells and how it was computed
+    pub(crate) fn safe_cells_breakdown(&self, planet_state: &mut PlanetState, now: i64) -> SafeCells {
+        let observation = self.observation(planet_state, now);
+        let safe_cells = match self.policy() {
+            Some(policy) => self.bound_policy_safe_cells(policy.safe_cells(&observation), &observation),
+            None => self.strategy().safe_cells(&observation, self.config()),
+        };
+        self.finish_safe_cells(safe_cells, planet_state.id())
+    }
+
+    ///Function that applies the margin to the safe cells and logs every stage
+    pub(crate) fn finish_safe_cells(&self, safe_cells: SafeCells, id: u32) -> SafeCells {
+        let safe_cells = self.apply_margin(safe_cells);
+        self.metrics_handle()
+            .record(|m| m.safe_cells = safe_cells.value);
+
+        for adjustment in &safe_cells.adjustments {
+            let reason = match adjustment.stage {
+                SafeCellsStage::Base => "base threshold",
+                SafeCellsStage::AsteroidNear => "the asteroid may come soon",
+                SafeCellsStage::SunrayImminent => "I estimate that a sunray may arrive",
+                SafeCellsStage::ExplorerDemand => "explorers on the planet",
+                SafeCellsStage::Margin => "adaptive margin",
+            };
+            self.log(
+                LogCategory::SafeCells,
+                Some(Participant::new(ActorType::User, id)),
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Debug,
+                [(
+                    "message",
+                    format!(
+                        "Safe cells, {}: {:+} (asked {:+}), {} cells preserved",
+                        reason, adjustment.applied, adjustment.requested, adjustment.value
+                    ),
+                )],
+            );
         }
+        safe_cells
     }
 
     ///Function for generating carbon if there are more than 'safe_cells' cells charged
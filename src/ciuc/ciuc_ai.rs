@@ -1,3 +1,19 @@
+use crate::ciuc::clock::Clock;
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::decision::{CarbonDecision, DecisionLog};
+use crate::ciuc::logging::CiucLogger;
+use crate::ciuc::metrics::MetricsHandle;
+use crate::ciuc::outlier::OutlierFilter;
+use crate::ciuc::periodicity::PeriodTracker;
+use crate::ciuc::policy::Policy;
+use crate::ciuc::q_learning::{QLearningPolicy, QTableHandle};
+use crate::ciuc::regime::PageHinkley;
+use crate::ciuc::stockpile::CarbonStockpile;
+use crate::ciuc::strategy::{CiucStrategy, DefaultStrategy};
+use crate::ciuc::trace::TraceRecorder;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AIState {
     SafeState,      //Safe state, the planet generates less resources
     StatisticState, //Statistic state, the planet is less conservative: it generates resources depending on 'estimate_asteroid_ms' and 'estimate_sunray_ms'
@@ -8,26 +24,99 @@ pub struct CiucAI {
     number_explorers: usize,
     count_asteroids: u32,
     count_sunrays: u32,
-    last_time_sunray: i64,
-    last_time_asteroid: i64,
+    last_time_sunray: Option<i64>, // None until the first sunray
+    last_time_asteroid: Option<i64>, // None until the first asteroid
     estimate_sunray_ms: f64,
     estimate_asteroid_ms: f64,
+    sunray_outliers: OutlierFilter,   // Recent sunray intervals, to detect the outliers
+    asteroid_outliers: OutlierFilter, // Recent asteroid intervals, to detect the outliers
+    sunray_regime: PageHinkley,       // Test for a change of the sunray rate
+    asteroid_regime: PageHinkley,     // Test for a change of the asteroid rate
+    sunray_period: PeriodTracker,     // Recent sunray intervals, to detect a fixed timer
+    asteroid_period: PeriodTracker,   // Recent asteroid intervals, to detect a fixed timer
+    safe_cells_margin: i32, // Cells added to the safe cells by the online adaptation
+    margin_changed_ms: Option<i64>, // Time of the last change of the margin
+    paused_at: Option<i64>,         // Time of the stop, while the AI is stopped
+    logger: CiucLogger,
+    decisions: DecisionLog,
+    last_decision: Option<CarbonDecision>, // Kept even when the history is disabled, for the log payload
+    metrics: MetricsHandle,
+    stockpile: CarbonStockpile,
+    config: CiucConfig,
+    clock: Clock,
+    trace: Option<TraceRecorder>, // Open only while recording
+    strategy: Box<dyn CiucStrategy>,
+    policy: Option<Box<dyn Policy>>, // Replaces the safe cells of the strategy when set
+    q_table: Option<QTableHandle>,   // Table of the Q-learning policy built from the configuration
 }
 
 impl CiucAI {
-    pub(crate) fn new() -> Self {
+    ///Function that creates the AI with the default configuration
+    pub fn new() -> Self {
+        CiucAI::with_config(CiucConfig::default())
+    }
+
+    ///Function that creates the AI with the given configuration
+    pub fn with_config(config: CiucConfig) -> Self {
+        CiucAI::with_clock(config, Clock::system())
+    }
+
+    ///Function that creates the AI reading the time from the given clock, e.g. a virtual one in a simulation
+    pub fn with_clock(config: CiucConfig, clock: Clock) -> Self {
+        // The priors count as already observed intervals
+        let (estimate_sunray_ms, count_sunrays) = config
+            .sunray_prior
+            .map_or((0.0, 0), |p| (p.mean_interval_ms, p.pseudo_count));
+        let (estimate_asteroid_ms, count_asteroids) = config
+            .asteroid_prior
+            .map_or((0.0, 0), |p| (p.mean_interval_ms, p.pseudo_count));
+        let q_learning = config.q_learning.clone().map(QLearningPolicy::new);
+        let q_table = q_learning.as_ref().map(|p| p.table());
         CiucAI {
             state: AIState::SafeState,
             number_explorers: 0,
-            count_asteroids: 0,
-            count_sunrays: 0,
-            last_time_sunray: 0,
-            last_time_asteroid: 0,
-            estimate_asteroid_ms: 0.0,
-            estimate_sunray_ms: 0.0,
+            count_asteroids,
+            count_sunrays,
+            last_time_sunray: None,
+            last_time_asteroid: None,
+            estimate_asteroid_ms,
+            estimate_sunray_ms,
+            sunray_outliers: OutlierFilter::default(),
+            asteroid_outliers: OutlierFilter::default(),
+            sunray_regime: PageHinkley::default(),
+            asteroid_regime: PageHinkley::default(),
+            sunray_period: PeriodTracker::default(),
+            asteroid_period: PeriodTracker::default(),
+            safe_cells_margin: 0,
+            margin_changed_ms: None,
+            paused_at: None,
+            logger: CiucLogger::new(config.logging.clone()),
+            decisions: DecisionLog::new(config.decision_history),
+            last_decision: None,
+            metrics: MetricsHandle::new(clock.clone()),
+            stockpile: CarbonStockpile::new(config.stockpile_capacity),
+            config,
+            clock,
+            trace: None,
+            strategy: Box::new(DefaultStrategy),
+            policy: q_learning.map(|p| Box::new(p) as Box<dyn Policy>),
+            q_table,
         }
     }
 
+    ///Function that replaces the decisions of the AI with the given strategy
+    pub fn with_strategy(mut self, strategy: Box<dyn CiucStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    ///Function that replaces the safe-cell thresholds with the given policy
+    pub fn with_policy(mut self, policy: Box<dyn Policy>) -> Self {
+        self.policy = Some(policy);
+        self.q_table = None;
+        self
+    }
+
     // ---------------- Getters ----------------
     pub(crate) fn state(&self) -> &AIState {
         &self.state
@@ -45,11 +134,11 @@ impl CiucAI {
         self.count_sunrays
     }
 
-    pub(crate) fn last_time_sunray(&self) -> i64 {
+    pub(crate) fn last_time_sunray(&self) -> Option<i64> {
         self.last_time_sunray
     }
 
-    pub(crate) fn last_time_asteroid(&self) -> i64 {
+    pub(crate) fn last_time_asteroid(&self) -> Option<i64> {
         self.last_time_asteroid
     }
 
@@ -61,8 +150,127 @@ impl CiucAI {
         self.estimate_asteroid_ms
     }
 
+    pub(crate) fn safe_cells_margin(&self) -> i32 {
+        self.safe_cells_margin
+    }
+
+    pub(crate) fn margin_changed_ms(&self) -> Option<i64> {
+        self.margin_changed_ms
+    }
+
+    pub(crate) fn paused_at(&self) -> Option<i64> {
+        self.paused_at
+    }
+
+    pub(crate) fn logger(&self) -> &CiucLogger {
+        &self.logger
+    }
+
+    pub(crate) fn config(&self) -> &CiucConfig {
+        &self.config
+    }
+
+    ///Function that returns the current time of the AI clock
+    pub(crate) fn now(&self) -> i64 {
+        self.clock.now_ms()
+    }
+
+    pub(crate) fn avoid_sunray_waste(&self) -> bool {
+        self.config.avoid_sunray_waste
+    }
+
+    ///Function that returns the number of carbon units ready to be sent to the explorers
+    pub fn carbon_stock_len(&self) -> usize {
+        self.stockpile.len()
+    }
+
+    pub(crate) fn stockpile(&self) -> &CarbonStockpile {
+        &self.stockpile
+    }
+
+    pub(crate) fn stockpile_mut(&mut self) -> &mut CarbonStockpile {
+        &mut self.stockpile
+    }
+
+    pub(crate) fn prefill_stockpile(&self) -> bool {
+        self.config.prefill_stockpile
+    }
+
+    pub(crate) fn sunray_outliers_mut(&mut self) -> &mut OutlierFilter {
+        &mut self.sunray_outliers
+    }
+
+    pub(crate) fn asteroid_outliers_mut(&mut self) -> &mut OutlierFilter {
+        &mut self.asteroid_outliers
+    }
+
+    pub(crate) fn sunray_regime_mut(&mut self) -> &mut PageHinkley {
+        &mut self.sunray_regime
+    }
+
+    pub(crate) fn asteroid_regime_mut(&mut self) -> &mut PageHinkley {
+        &mut self.asteroid_regime
+    }
+
+    pub(crate) fn sunray_period(&self) -> &PeriodTracker {
+        &self.sunray_period
+    }
+
+    pub(crate) fn asteroid_period(&self) -> &PeriodTracker {
+        &self.asteroid_period
+    }
+
+    pub(crate) fn sunray_period_mut(&mut self) -> &mut PeriodTracker {
+        &mut self.sunray_period
+    }
+
+    pub(crate) fn asteroid_period_mut(&mut self) -> &mut PeriodTracker {
+        &mut self.asteroid_period
+    }
+
+    pub(crate) fn trace_mut(&mut self) -> &mut Option<TraceRecorder> {
+        &mut self.trace
+    }
+
+    pub(crate) fn strategy(&self) -> &dyn CiucStrategy {
+        self.strategy.as_ref()
+    }
+
+    pub(crate) fn policy(&self) -> Option<&dyn Policy> {
+        self.policy.as_deref()
+    }
+
+    pub(crate) fn policy_mut(&mut self) -> Option<&mut (dyn Policy + 'static)> {
+        self.policy.as_deref_mut()
+    }
+
+    ///Function that returns a handle to the table of the Q-learning policy, if configured
+    pub fn q_table(&self) -> Option<QTableHandle> {
+        self.q_table.clone()
+    }
+
+    pub(crate) fn metrics_handle(&self) -> &MetricsHandle {
+        &self.metrics
+    }
+
+    ///Function that returns a handle to the history of the carbon decisions, usable after the AI is moved into the planet
+    pub fn decision_log(&self) -> DecisionLog {
+        self.decisions.clone()
+    }
+
+    ///Function that returns a handle to the metrics, usable after the AI is moved into the planet
+    pub fn metrics(&self) -> MetricsHandle {
+        self.metrics.clone()
+    }
+
+    ///Function that returns the trace of the last carbon request
+    pub fn last_decision(&self) -> Option<CarbonDecision> {
+        self.last_decision.clone()
+    }
+
     // ---------------- Setters ----------------
     pub(crate) fn set_state(&mut self, state: AIState) {
+        self.metrics.record(|m| m.record_state(state, self.now()));
         self.state = state;
     }
 
@@ -78,12 +286,20 @@ impl CiucAI {
         self.count_sunrays += 1;
     }
 
+    pub(crate) fn set_count_asteroids(&mut self, n: u32) {
+        self.count_asteroids = n;
+    }
+
+    pub(crate) fn set_count_sunrays(&mut self, n: u32) {
+        self.count_sunrays = n;
+    }
+
     pub(crate) fn set_last_time_sunray(&mut self, t: i64) {
-        self.last_time_sunray = t;
+        self.last_time_sunray = Some(t);
     }
 
     pub(crate) fn set_last_time_asteroid(&mut self, t: i64) {
-        self.last_time_asteroid = t;
+        self.last_time_asteroid = Some(t);
     }
 
     pub(crate) fn set_estimate_sunray_ms(&mut self, e: f64) {
@@ -93,4 +309,28 @@ impl CiucAI {
     pub(crate) fn set_estimate_asteroid_ms(&mut self, e: f64) {
         self.estimate_asteroid_ms = e;
     }
+
+    pub(crate) fn set_safe_cells_margin(&mut self, margin: i32) {
+        self.safe_cells_margin = margin;
+    }
+
+    pub(crate) fn set_margin_changed_ms(&mut self, t: i64) {
+        self.margin_changed_ms = Some(t);
+    }
+
+    ///Function for recording the decision of a carbon request, in the history too if enabled
+    pub(crate) fn record_decision(&mut self, decision: CarbonDecision) {
+        self.decisions.push(decision.clone());
+        self.last_decision = Some(decision);
+    }
+
+    pub(crate) fn set_paused_at(&mut self, t: Option<i64>) {
+        self.paused_at = t;
+    }
+}
+
+impl Default for CiucAI {
+    fn default() -> Self {
+        CiucAI::new()
+    }
 }
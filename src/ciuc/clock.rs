@@ -0,0 +1,65 @@
+use crate::ciuc::esteem::now_ms;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Instant;
+
+/// Source of the time used by the AI: the system clock, or a virtual clock moved by hand (e.g. by the simulator).
+/// Cloning it gives another handle to the same clock.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    source: Source,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    // Wall-clock time at creation, then advanced by a monotonic clock: it never goes backwards
+    System { start: Instant, start_ms: i64 },
+    Virtual(Arc<AtomicI64>),
+}
+
+impl Clock {
+    ///Function that returns the system clock, immune to the jumps of the wall clock after its creation
+    pub fn system() -> Self {
+        Clock {
+            source: Source::System {
+                start: Instant::now(),
+                start_ms: now_ms(),
+            },
+        }
+    }
+
+    ///Function that returns a virtual clock starting at 'start_ms', it only moves with 'set' and 'advance'
+    pub fn manual(start_ms: i64) -> Self {
+        Clock {
+            source: Source::Virtual(Arc::new(AtomicI64::new(start_ms))),
+        }
+    }
+
+    ///Function that returns the current time in milliseconds
+    pub fn now_ms(&self) -> i64 {
+        match &self.source {
+            Source::System { start, start_ms } => start_ms + start.elapsed().as_millis() as i64,
+            Source::Virtual(t) => t.load(Ordering::SeqCst),
+        }
+    }
+
+    ///Function for moving a virtual clock to 'ms', it does nothing on the system clock
+    pub fn set(&self, ms: i64) {
+        if let Source::Virtual(t) = &self.source {
+            t.store(ms, Ordering::SeqCst);
+        }
+    }
+
+    ///Function for moving a virtual clock forward by 'ms', it does nothing on the system clock
+    pub fn advance(&self, ms: i64) {
+        if let Source::Virtual(t) = &self.source {
+            t.fetch_add(ms, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::system()
+    }
+}
@@ -0,0 +1,93 @@
+use crate::ciuc::adaptive::AdaptiveMarginConfig;
+use crate::ciuc::carbon::SafeCellsConfig;
+use crate::ciuc::esteem::Prior;
+use crate::ciuc::logging::LogConfig;
+use crate::ciuc::outlier::OutlierConfig;
+use crate::ciuc::periodicity::PeriodicityConfig;
+use crate::ciuc::q_learning::QLearningConfig;
+use crate::ciuc::regime::RegimeChangeConfig;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Configuration of the Ciuc AI, chosen at planet creation.
+/// Missing fields take the default value, so older saved configurations still load
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CiucConfig {
+    /// Verbosity and sampling of the logged events
+    pub logging: LogConfig,
+    /// Charged cells preserved for the planet, and when
+    pub safe_cells: SafeCellsConfig,
+    /// Adapt the safe-cell margin to the near-deaths, `None` keeps the thresholds fixed
+    pub adaptive_margin: Option<AdaptiveMarginConfig>,
+    /// Choose the safe cells with a Q-learning policy instead of the thresholds
+    pub q_learning: Option<QLearningConfig>,
+    /// Allow leaving 'SafeState' once the estimates are usable (false keeps the planet always safe)
+    pub statistic_state: bool,
+    /// Number of carbon decisions kept in the history (0 disables it)
+    pub decision_history: usize,
+    /// Free a charged cell (into a rocket or carbon) when a sunray would hit full cells
+    pub avoid_sunray_waste: bool,
+    /// Maximum number of carbon units generated in advance
+    pub stockpile_capacity: usize,
+    /// Fill the stockpile with the charged cells above the safe target
+    pub prefill_stockpile: bool,
+    /// File where the learned state is saved when the planet is stopped
+    pub state_path: Option<PathBuf>,
+    /// Known sunray schedule, seeds 'estimate_sunray_ms'
+    pub sunray_prior: Option<Prior>,
+    /// Known asteroid schedule, seeds 'estimate_asteroid_ms'
+    pub asteroid_prior: Option<Prior>,
+    /// Longest interval used as a sample: longer ones (a pause, a clock jump) are clamped to it
+    pub max_interval_ms: i64,
+    /// Keep the intervals far from the recent ones out of the estimators, `None` uses every interval
+    pub outliers: Option<OutlierConfig>,
+    /// Restart an estimate in 'SafeState' when its event rate changes, `None` lets the moving average follow it
+    pub regime_change: Option<RegimeChangeConfig>,
+    /// Predict the next sunray and asteroid of a fixed timer, `None` only uses the mean intervals
+    pub periodicity: Option<PeriodicityConfig>,
+    /// Pause of the AI (from a stop to the next start) after which it restarts the estimation in 'SafeState',
+    /// `None` resumes in the same state
+    pub pause_reset_ms: Option<i64>,
+    /// File where every incoming event is recorded as JSON lines, to be replayed later
+    pub trace_path: Option<PathBuf>,
+}
+
+impl Default for CiucConfig {
+    fn default() -> Self {
+        CiucConfig {
+            logging: LogConfig::default(),
+            safe_cells: SafeCellsConfig::default(),
+            adaptive_margin: None,
+            q_learning: None,
+            statistic_state: true,
+            decision_history: 64,
+            avoid_sunray_waste: false,
+            stockpile_capacity: 0,
+            prefill_stockpile: false,
+            state_path: None,
+            sunray_prior: None,
+            asteroid_prior: None,
+            max_interval_ms: 600_000,
+            outliers: None,
+            regime_change: None,
+            periodicity: None,
+            pause_reset_ms: None,
+            trace_path: None,
+        }
+    }
+}
+
+impl CiucConfig {
+    ///Function that returns the settings that don't work together, they are logged as warnings when the AI starts
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.avoid_sunray_waste && self.stockpile_capacity == 0 {
+            warnings.push(
+                "'avoid_sunray_waste' needs a 'stockpile_capacity' above 0: with a rocket built, no cell can be freed"
+                    .to_string(),
+            );
+        }
+        warnings
+    }
+}
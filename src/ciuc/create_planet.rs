@@ -1,4 +1,6 @@
 use crate::CiucAI;
+use crate::ciuc::CiucConfig;
+use crate::ciuc::persistence::CiucAIState;
 use common_game::components::planet::{Planet, PlanetAI, PlanetType};
 use common_game::components::resource::BasicResourceType;
 use common_game::protocols::orchestrator_planet::OrchestratorToPlanet;
@@ -12,8 +14,58 @@ pub fn create_planet(
     rx_explorer: Receiver<ExplorerToPlanet>,
     id: u32,
 ) -> Planet {
-    let ai_concrete = CiucAI::new();
-    let ai_box: Box<dyn PlanetAI> = Box::new(ai_concrete);
+    create_planet_with_config(
+        rx_orchestrator,
+        tx_orchestrator,
+        rx_explorer,
+        id,
+        CiucConfig::default(),
+    )
+}
+
+///Function for creating the planet with a custom configuration of the AI
+pub fn create_planet_with_config(
+    rx_orchestrator: Receiver<OrchestratorToPlanet>,
+    tx_orchestrator: Sender<PlanetToOrchestrator>,
+    rx_explorer: Receiver<ExplorerToPlanet>,
+    id: u32,
+    config: CiucConfig,
+) -> Planet {
+    create_planet_with_ai(
+        rx_orchestrator,
+        tx_orchestrator,
+        rx_explorer,
+        id,
+        CiucAI::with_config(config),
+    )
+}
+
+///Function for creating the planet with an AI warm-started from a saved state
+pub fn create_planet_from_state(
+    rx_orchestrator: Receiver<OrchestratorToPlanet>,
+    tx_orchestrator: Sender<PlanetToOrchestrator>,
+    rx_explorer: Receiver<ExplorerToPlanet>,
+    id: u32,
+    saved: CiucAIState,
+) -> Planet {
+    create_planet_with_ai(
+        rx_orchestrator,
+        tx_orchestrator,
+        rx_explorer,
+        id,
+        CiucAI::from_state(saved),
+    )
+}
+
+///Function for creating the planet with an AI built by the caller, who can keep its handles (e.g. the decision log)
+pub fn create_planet_with_ai(
+    rx_orchestrator: Receiver<OrchestratorToPlanet>,
+    tx_orchestrator: Sender<PlanetToOrchestrator>,
+    rx_explorer: Receiver<ExplorerToPlanet>,
+    id: u32,
+    ai: CiucAI,
+) -> Planet {
+    let ai_box: Box<dyn PlanetAI> = Box::new(ai);
 
     let gen_rules = vec![BasicResourceType::Carbon];
     let comb_rules = vec![];
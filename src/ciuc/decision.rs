@@ -0,0 +1,149 @@
+use crate::CiucAI;
+use crate::ciuc::AIState;
+use crate::ciuc::carbon::SafeCellsAdjustment;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Trace of a single carbon request: everything the AI looked at to grant or refuse it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarbonDecision {
+    pub explorer_id: u32,
+    pub state: AIState,
+    pub charged_cells: u32,
+    pub estimate_sunray_ms: f64,
+    pub estimate_asteroid_ms: f64,
+    /// `None` if no sunray has been received yet
+    pub since_last_sunray_ms: Option<i64>,
+    /// `None` if no asteroid has been received yet
+    pub since_last_asteroid_ms: Option<i64>,
+    /// Time until the predicted sunray, `None` if the sunrays are not periodic
+    pub until_next_sunray_ms: Option<i64>,
+    /// Time until the predicted asteroid, `None` if the asteroids are not periodic
+    pub until_next_asteroid_ms: Option<i64>,
+    /// Cells removed from the threshold because a sunray is imminent
+    pub sunray_adjustment: u32,
+    /// Whether the asteroid was considered far, `None` in 'SafeState'
+    pub asteroid_far: Option<bool>,
+    /// Cells added (or removed, if negative) by the online adaptation of the margin
+    pub margin: i32,
+    /// Final threshold: carbon is generated only with more charged cells than this
+    pub safe_cells: u32,
+    /// Every stage of the safe-cell computation, in order
+    pub adjustments: Vec<SafeCellsAdjustment>,
+    pub granted: bool,
+    /// Whether the carbon was taken from the stock instead of being generated
+    pub from_stock: bool,
+    /// Reason of the refusal, `None` if granted
+    pub refusal: Option<String>,
+}
+
+impl CarbonDecision {
+    ///Function that returns the decision as key-value pairs, to be attached to a log event
+    pub fn to_payload(&self) -> Vec<(String, String)> {
+        let optional = |v: Option<i64>| v.map_or("none".to_string(), |v| v.to_string());
+        vec![
+            ("explorer_id".to_string(), self.explorer_id.to_string()),
+            ("state".to_string(), format!("{:?}", self.state)),
+            ("charged_cells".to_string(), self.charged_cells.to_string()),
+            (
+                "estimate_sunray_ms".to_string(),
+                self.estimate_sunray_ms.to_string(),
+            ),
+            (
+                "estimate_asteroid_ms".to_string(),
+                self.estimate_asteroid_ms.to_string(),
+            ),
+            (
+                "since_last_sunray_ms".to_string(),
+                optional(self.since_last_sunray_ms),
+            ),
+            (
+                "since_last_asteroid_ms".to_string(),
+                optional(self.since_last_asteroid_ms),
+            ),
+            (
+                "until_next_sunray_ms".to_string(),
+                optional(self.until_next_sunray_ms),
+            ),
+            (
+                "until_next_asteroid_ms".to_string(),
+                optional(self.until_next_asteroid_ms),
+            ),
+            (
+                "sunray_adjustment".to_string(),
+                self.sunray_adjustment.to_string(),
+            ),
+            (
+                "asteroid_far".to_string(),
+                self.asteroid_far
+                    .map_or("none".to_string(), |v| v.to_string()),
+            ),
+            ("margin".to_string(), self.margin.to_string()),
+            ("safe_cells".to_string(), self.safe_cells.to_string()),
+            (
+                "adjustments".to_string(),
+                self.adjustments
+                    .iter()
+                    .map(|a| format!("{:?} {:+}", a.stage, a.applied))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ("granted".to_string(), self.granted.to_string()),
+            ("from_stock".to_string(), self.from_stock.to_string()),
+            (
+                "refusal".to_string(),
+                self.refusal.clone().unwrap_or_else(|| "none".to_string()),
+            ),
+        ]
+    }
+}
+
+/// Shared, bounded history of the carbon decisions of an AI.
+/// Cloning it gives another handle to the same history, readable while the planet is running.
+#[derive(Debug, Clone)]
+pub struct DecisionLog {
+    decisions: Arc<Mutex<VecDeque<CarbonDecision>>>,
+    capacity: usize,
+}
+
+impl DecisionLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        DecisionLog {
+            decisions: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub(crate) fn push(&self, decision: CarbonDecision) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut decisions = self.decisions.lock().unwrap();
+        if decisions.len() == self.capacity {
+            decisions.pop_front();
+        }
+        decisions.push_back(decision);
+    }
+
+    ///Function that returns the most recent decision
+    pub fn last(&self) -> Option<CarbonDecision> {
+        self.decisions.lock().unwrap().back().cloned()
+    }
+
+    ///Function that returns the stored decisions, from the oldest
+    pub fn all(&self) -> Vec<CarbonDecision> {
+        self.decisions.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl CiucAI {
+    ///Function that returns a log payload with the message and the trace of the last carbon decision,
+    ///whatever the size of the history
+    pub(crate) fn decision_payload(&self, message: impl Into<String>) -> Vec<(String, String)> {
+        let mut payload = vec![("message".to_string(), message.into())];
+        if let Some(decision) = self.last_decision() {
+            payload.extend(decision.to_payload());
+        }
+        payload
+    }
+}
@@ -1,7 +1,42 @@
 use crate::CiucAI;
 use crate::ciuc::AIState;
+use crate::ciuc::logging::LogCategory;
+use crate::ciuc::outlier::OutlierFilter;
+use crate::ciuc::periodicity::PeriodTracker;
+use crate::ciuc::regime::PageHinkley;
+use common_game::components::planet::PlanetState;
 use common_game::logging::Participant;
 use common_game::logging::{ActorType, Channel, EventType};
+use serde::{Deserialize, Serialize};
+
+/// Weight of a new sample in the exponential moving average of the intervals
+const EMA_ALPHA: f64 = 0.3;
+
+/// Prior knowledge of an event interval, used to seed the estimate before any sample is observed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Prior {
+    /// Expected time between two events
+    pub mean_interval_ms: f64,
+    /// How many observed samples the prior is worth
+    pub pseudo_count: u32,
+}
+
+///Function that returns the weight of a new sample, 'count' includes the prior: with a prior, the samples are
+///averaged with it as if it were 'pseudo_count' samples until they outnumber it, then the usual moving average takes over
+fn sample_weight(count: u32, prior: Option<&Prior>) -> f64 {
+    match prior {
+        Some(p) if p.pseudo_count > 0 => {
+            let average = 1.0 / (count as f64 + 1.0);
+            if count < 2 * p.pseudo_count {
+                average
+            } else {
+                EMA_ALPHA.max(average)
+            }
+        }
+        _ => EMA_ALPHA,
+    }
+}
+
 pub fn update_ema(prev: f64, sample: f64, alpha: f64) -> f64 {
     alpha * sample + (1.0 - alpha) * prev
 }
@@ -17,18 +52,27 @@ impl CiucAI {
     /// Function for updating sunray esteem
     pub(crate) fn update_sunray_esteem(&mut self, now_ms: i64, id: u32) {
         let prev_esteem_for_log = self.estimate_sunray_ms();
-        if self.last_time_sunray() > 0 {
+        let sample = self
+            .interval_sample(self.last_time_sunray(), now_ms, id, "sunray")
+            .inspect(|&delta| self.detect_sunray_regime_change(delta, id))
+            .and_then(|delta| self.filter_sunray_sample(delta, id))
+            .inspect(|&delta| self.observe_sunray_period(delta));
+        if let Some(delta) = sample {
+            self.metrics_handle()
+                .record(|m| m.sunray_interval_ms.observe(delta));
             if self.count_sunrays() == 0 {
-                self.set_estimate_sunray_ms((now_ms - self.last_time_sunray()) as f64);
+                self.set_estimate_sunray_ms(delta);
                 self.increment_count_sunrays()
             } else {
-                let delta = (now_ms - self.last_time_sunray()) as f64;
-                self.set_estimate_sunray_ms(update_ema(self.estimate_sunray_ms(), delta, 0.3));
+                let alpha =
+                    sample_weight(self.count_sunrays(), self.config().sunray_prior.as_ref());
+                self.set_estimate_sunray_ms(update_ema(self.estimate_sunray_ms(), delta, alpha));
                 self.increment_count_sunrays()
             }
         }
         self.set_last_time_sunray(now_ms);
-        CiucAI::log_event(
+        self.log(
+            LogCategory::Esteem,
             Some(Participant::new(ActorType::User, id)),
             None,
             EventType::InternalPlanetAction,
@@ -47,18 +91,33 @@ impl CiucAI {
     /// Function for updating asteroid esteem
     pub(crate) fn update_asteroid_esteem(&mut self, now_ms: i64, id: u32) {
         let prev_asteroid_esteem_for_log = self.estimate_asteroid_ms();
-        if self.last_time_asteroid() > 0 {
+        let sample = self
+            .interval_sample(self.last_time_asteroid(), now_ms, id, "asteroid")
+            .inspect(|&delta| self.detect_asteroid_regime_change(delta, id))
+            .and_then(|delta| self.filter_asteroid_sample(delta, id))
+            .inspect(|&delta| self.observe_asteroid_period(delta));
+        if let Some(delta) = sample {
+            self.metrics_handle()
+                .record(|m| m.asteroid_interval_ms.observe(delta));
             if self.count_asteroids() == 0 {
-                self.set_estimate_asteroid_ms((now_ms - self.last_time_asteroid()) as f64);
+                self.set_estimate_asteroid_ms(delta);
                 self.increment_count_asteroids()
             } else {
-                let delta = (now_ms - self.last_time_asteroid()) as f64;
-                self.set_estimate_asteroid_ms(update_ema(self.estimate_asteroid_ms(), delta, 0.3));
+                let alpha = sample_weight(
+                    self.count_asteroids(),
+                    self.config().asteroid_prior.as_ref(),
+                );
+                self.set_estimate_asteroid_ms(update_ema(
+                    self.estimate_asteroid_ms(),
+                    delta,
+                    alpha,
+                ));
                 self.increment_count_asteroids()
             }
         }
         self.set_last_time_asteroid(now_ms);
-        CiucAI::log_event(
+        self.log(
+            LogCategory::Esteem,
             Some(Participant::new(ActorType::User, id)),
             None,
             EventType::InternalPlanetAction,
@@ -74,35 +133,88 @@ impl CiucAI {
         );
     }
 
-    ///Function for changing state
-    pub(crate) fn change_state(&mut self, id: u32) {
-        // Return to safe zone if in StatisticState and asteroid threat is greater than sunray opportunity
-        if matches!(self.state(), AIState::StatisticState)
-            && self.estimate_asteroid_ms() < self.estimate_sunray_ms()
-        {
-            self.set_state(AIState::SafeState);
-            CiucAI::log_event(
-                Some(Participant::new(ActorType::User, id)),
+    ///Function that returns the interval since the previous event, if it can be used as a sample:
+    ///a negative interval (the clock went backwards) is discarded, an absurdly long one is clamped
+    fn interval_sample(&self, last: Option<i64>, now_ms: i64, id: u32, event: &str) -> Option<f64> {
+        let delta = now_ms - last?;
+        let max_interval_ms = self.config().max_interval_ms;
+        let (sample, message) = if delta < 0 {
+            self.metrics_handle()
+                .record(|m| m.interval_samples_discarded += 1);
+            (
                 None,
-                EventType::InternalPlanetAction,
-                Channel::Debug,
-                [("message", "Changed AI's state into safe")],
-            );
+                format!("Discarded a negative {} interval of {}ms", event, delta),
+            )
+        } else if delta > max_interval_ms {
+            self.metrics_handle()
+                .record(|m| m.interval_samples_clamped += 1);
+            (
+                Some(max_interval_ms as f64),
+                format!(
+                    "Clamped a {} interval of {}ms to {}ms",
+                    event, delta, max_interval_ms
+                ),
+            )
+        } else {
+            return Some(delta as f64);
+        };
+        self.log(
+            LogCategory::Esteem,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Warning,
+            [("message", message)],
+        );
+        sample
+    }
+
+    ///Function for going back to 'SafeState' and forgetting how many intervals were observed:
+    ///the estimates are replaced by the next intervals, and the state is kept until they are enough again
+    pub(crate) fn restart_estimation(&mut self, id: u32, reason: &str) {
+        self.set_count_sunrays(0);
+        self.set_count_asteroids(0);
+        *self.sunray_outliers_mut() = OutlierFilter::default();
+        *self.asteroid_outliers_mut() = OutlierFilter::default();
+        *self.sunray_regime_mut() = PageHinkley::default();
+        *self.asteroid_regime_mut() = PageHinkley::default();
+        *self.sunray_period_mut() = PeriodTracker::default();
+        *self.asteroid_period_mut() = PeriodTracker::default();
+        if *self.state() != AIState::SafeState {
+            self.set_state(AIState::SafeState);
         }
-        // Transition to StatisticState if enough data is collected and asteroid threat is less than sunray opportunity
-        else if matches!(self.state(), AIState::SafeState)
-            && self.count_asteroids() >= 3
-            && self.count_sunrays() >= 3
-            && self.estimate_asteroid_ms() >= self.estimate_sunray_ms()
-        {
-            self.set_state(AIState::StatisticState);
-            CiucAI::log_event(
-                Some(Participant::new(ActorType::User, id)),
-                None,
-                EventType::InternalPlanetAction,
-                Channel::Debug,
-                [("message", "Changed AI's state into statistic")],
-            );
+        self.log(
+            LogCategory::State,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Info,
+            [(
+                "message",
+                format!("Restarted the estimation in safe state {}", reason),
+            )],
+        );
+    }
+
+    ///Function for changing state
+    pub(crate) fn change_state(&mut self, planet_state: &PlanetState) {
+        let observation = self.observation(planet_state, self.now());
+        let next = self.strategy().next_state(&observation, self.config());
+        if next == *self.state() {
+            return;
         }
+        self.set_state(next);
+        let message = match next {
+            AIState::SafeState => "Changed AI's state into safe",
+            AIState::StatisticState => "Changed AI's state into statistic",
+        };
+        self.log(
+            LogCategory::State,
+            Some(Participant::new(ActorType::User, planet_state.id())),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Debug,
+            [("message", message)],
+        );
     }
 }
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+// Events received by the planet and its reactions, shared by the simulation and the trace of a real run
+
+/// What happens to the planet at a given time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimEventKind {
+    Sunray,
+    Asteroid,
+    ExplorerArrival {
+        explorer_id: u32,
+    },
+    ExplorerDeparture {
+        explorer_id: u32,
+    },
+    /// The explorer is registered on the planet first, if it isn't already
+    CarbonRequest {
+        explorer_id: u32,
+    },
+    /// The explorer is registered on the planet first, if it isn't already
+    AvailableCellsRequest {
+        explorer_id: u32,
+    },
+    /// Any other explorer message: it changes nothing on this planet
+    OtherExplorerRequest {
+        explorer_id: u32,
+    },
+}
+
+/// How the planet reacted to an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimOutcome {
+    SunrayCharged,
+    SunrayWasted, // All the cells were already charged
+    AsteroidDeflected,
+    PlanetDestroyed,
+    ExplorerArrived,
+    ExplorerDeparted,
+    CarbonGranted,
+    CarbonRefused,
+    AvailableCells(u32),
+    Answered, // Answer to a request that doesn't involve a decision
+}
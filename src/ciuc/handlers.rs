@@ -1,10 +1,13 @@
 use crate::CiucAI;
-use crate::ciuc::esteem::now_ms;
+use crate::ciuc::logging::LogCategory;
+use crate::ciuc::policy::PolicyFeedback;
+use crate::ciuc::events::{SimEventKind, SimOutcome};
+use crate::ciuc::strategy::RocketTrigger;
+use crate::ciuc::trace::{explorer_event, explorer_outcome};
 use common_game::components::planet::DummyPlanetState;
 use common_game::components::planet::{PlanetAI, PlanetState};
 use common_game::components::resource::{
     BasicResource, BasicResourceType, Combinator, ComplexResourceRequest, Generator,
-    GenericResource,
 };
 use common_game::components::rocket::Rocket;
 use common_game::components::sunray::Sunray;
@@ -17,15 +20,34 @@ impl CiucAI {
     pub(crate) fn on_sunray(
         &mut self,
         planet_state: &mut PlanetState,
+        generator: &Generator,
         sunray: Sunray,
     ) -> Result<(), String> {
-        self.update_sunray_esteem(now_ms(), planet_state.id());
-        let res = self.charge_cell_with_sunray(planet_state, sunray)?;
-        let mess_build = self.build_rocket(planet_state);
+        self.metrics_handle().record(|m| m.sunrays_received += 1);
+        self.update_sunray_esteem(self.now(), planet_state.id());
+        self.relax_margin_if_safe(planet_state.id());
+        let was_saturated = self.is_saturated(planet_state);
+        // Make room for the sunray instead of wasting it on full cells
+        if self.avoid_sunray_waste()
+            && was_saturated
+            && let Err(e) = self.free_cell_for_sunray(planet_state, generator)
+        {
+            self.log(
+                LogCategory::Action,
+                Some(Participant::new(ActorType::User, planet_state.id())),
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Info,
+                [("message", e)],
+            );
+        }
+        self.charge_cell_with_sunray(planet_state, sunray)?;
+        let mess_build = self.try_build_rocket(planet_state, RocketTrigger::Sunray);
 
         match mess_build {
             Ok(_) => {
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Action,
                     Some(Participant::new(ActorType::User, planet_state.id())),
                     None,
                     EventType::InternalPlanetAction,
@@ -35,7 +57,8 @@ impl CiucAI {
             }
             Err(_) => {
                 // If the rocket is not built, it's not a real error, it just tried
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Action,
                     Some(Participant::new(ActorType::User, planet_state.id())),
                     None,
                     EventType::InternalPlanetAction,
@@ -45,21 +68,34 @@ impl CiucAI {
             }
         }
 
-        self.change_state(planet_state.id());
-        Ok(res)
+        self.change_state(planet_state);
+        self.fill_stockpile(planet_state, generator);
+        let last_cell_charged = !was_saturated && self.is_saturated(planet_state);
+        self.prevent_sunray_waste(planet_state, generator, last_cell_charged);
+        Ok(())
     }
 
-    pub(crate) fn on_asteroid(&mut self, planet_state: &mut PlanetState) -> Option<Rocket> // Returns rocket if deflected, None if destroyed
+    pub(crate) fn on_asteroid(
+        &mut self,
+        planet_state: &mut PlanetState,
+        generator: &Generator,
+    ) -> Option<Rocket> // Returns rocket if deflected, None if destroyed
     {
-        self.update_asteroid_esteem(now_ms(), planet_state.id()); // Update the estimate
+        self.update_asteroid_esteem(self.now(), planet_state.id()); // Update the estimate
+        self.relax_margin_if_safe(planet_state.id());
         let rocket = self.deflect_asteroid(planet_state);
-        if let Some(_) = rocket {
+        self.metrics_handle().record(|m| match rocket {
+            Some(_) => m.asteroids_survived += 1,
+            None => m.asteroids_fatal += 1,
+        });
+        if rocket.is_some() {
             // As soon as the rocket is used (unless the planet is dead) try to recreate it immediately (if no energy cell, it will be created with the next sunray)
-            let mess_build = self.build_rocket(planet_state);
+            let mess_build = self.try_build_rocket(planet_state, RocketTrigger::AsteroidDeflected);
 
             match mess_build {
                 Ok(_) => {
-                    CiucAI::log_event(
+                    self.log(
+                        LogCategory::Action,
                         Some(Participant::new(ActorType::User, planet_state.id())),
                         None,
                         EventType::InternalPlanetAction,
@@ -69,7 +105,8 @@ impl CiucAI {
                 }
                 Err(_) => {
                     // If the rocket is not built, it's not a real error, it just tried
-                    CiucAI::log_event(
+                    self.log(
+                        LogCategory::Action,
                         Some(Participant::new(ActorType::User, planet_state.id())),
                         None,
                         EventType::InternalPlanetAction,
@@ -79,14 +116,17 @@ impl CiucAI {
                 }
             }
 
-            self.change_state(planet_state.id()); // Change the state if an estimate is usable and the planet is not dead
+            self.change_state(planet_state); // Change the state if an estimate is usable and the planet is not dead
+            self.adapt_margin_after_asteroid(planet_state);
+            self.prevent_sunray_waste(planet_state, generator, false);
+            self.policy_feedback(PolicyFeedback::AsteroidDeflected);
+        } else {
+            self.policy_feedback(PolicyFeedback::PlanetDestroyed);
         }
         rocket
     }
-}
 
-impl PlanetAI for CiucAI {
-    fn handle_explorer_msg(
+    pub(crate) fn on_explorer_msg(
         &mut self,
         state: &mut PlanetState,
         generator: &Generator,
@@ -95,7 +135,8 @@ impl PlanetAI for CiucAI {
     ) -> Option<PlanetToExplorer> {
         match msg {
             ExplorerToPlanet::SupportedResourceRequest { explorer_id: e_id } => {
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::User, state.id())),
                     None,
                     EventType::MessageExplorerToPlanet,
@@ -103,7 +144,8 @@ impl PlanetAI for CiucAI {
                     [("message", "Supported resource requested")],
                 );
 
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::Explorer, state.id())),
                     Some(Participant::new(ActorType::Explorer, e_id)),
                     EventType::MessagePlanetToExplorer,
@@ -117,7 +159,8 @@ impl PlanetAI for CiucAI {
             }
 
             ExplorerToPlanet::SupportedCombinationRequest { explorer_id: e_id } => {
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::User, state.id())),
                     None,
                     EventType::MessageExplorerToPlanet,
@@ -125,7 +168,8 @@ impl PlanetAI for CiucAI {
                     [("message", "Supported combinations requested")],
                 );
 
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::Explorer, state.id())),
                     Some(Participant::new(ActorType::Explorer, e_id)),
                     EventType::MessagePlanetToExplorer,
@@ -142,22 +186,24 @@ impl PlanetAI for CiucAI {
                 resource: res_type,
             } => match res_type {
                 BasicResourceType::Carbon => {
-                    CiucAI::log_event(
+                    self.log(
+                        LogCategory::Protocol,
                         Some(Participant::new(ActorType::User, state.id())),
                         None,
                         EventType::MessageExplorerToPlanet,
                         Channel::Info,
                         [("message", "Generate carbon request")],
                     );
-                    let res = self.generate_carbon(state, generator);
+                    let res = self.generate_carbon(state, generator, e_id);
                     match res {
                         Ok(carbon) => {
-                            CiucAI::log_event(
+                            self.log(
+                                LogCategory::Protocol,
                                 Some(Participant::new(ActorType::Explorer, state.id())),
                                 Some(Participant::new(ActorType::Explorer, e_id)),
                                 EventType::MessagePlanetToExplorer,
                                 Channel::Info,
-                                [("message", "Sending carbon to explorer")],
+                                self.decision_payload("Sending carbon to explorer"),
                             );
 
                             Some(PlanetToExplorer::GenerateResourceResponse {
@@ -165,15 +211,17 @@ impl PlanetAI for CiucAI {
                             })
                         }
                         Err(err) => {
-                            CiucAI::log_event(
+                            self.log(
+                                LogCategory::Action,
                                 Some(Participant::new(ActorType::User, state.id())),
                                 None,
                                 EventType::InternalPlanetAction,
                                 Channel::Error,
-                                [("message", err)],
+                                self.decision_payload(err),
                             );
 
-                            CiucAI::log_event(
+                            self.log(
+                                LogCategory::Protocol,
                                 Some(Participant::new(ActorType::Explorer, state.id())),
                                 Some(Participant::new(ActorType::Explorer, e_id)),
                                 EventType::MessagePlanetToExplorer,
@@ -189,7 +237,8 @@ impl PlanetAI for CiucAI {
                     }
                 }
                 _ => {
-                    CiucAI::log_event(
+                    self.log(
+                        LogCategory::Protocol,
                         Some(Participant::new(ActorType::User, state.id())),
                         None,
                         EventType::MessageExplorerToPlanet,
@@ -207,7 +256,8 @@ impl PlanetAI for CiucAI {
                 explorer_id: _,
                 msg: mes,
             } => {
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::User, state.id())),
                     None,
                     EventType::MessageExplorerToPlanet,
@@ -215,7 +265,8 @@ impl PlanetAI for CiucAI {
                     [("message", "Combination request")],
                 );
 
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::User, state.id())),
                     None,
                     EventType::InternalPlanetAction,
@@ -284,7 +335,8 @@ impl PlanetAI for CiucAI {
             }
 
             ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: e_id } => {
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::User, state.id())),
                     None,
                     EventType::MessageExplorerToPlanet,
@@ -292,7 +344,8 @@ impl PlanetAI for CiucAI {
                     [("message", "Available energy cells requested")],
                 );
 
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Protocol,
                     Some(Participant::new(ActorType::Explorer, state.id())),
                     Some(Participant::new(ActorType::Explorer, e_id)),
                     EventType::MessagePlanetToExplorer,
@@ -305,8 +358,7 @@ impl PlanetAI for CiucAI {
                     .filter(|e| e.is_charged())
                     .count() as u32;
 
-                let available_cells =
-                    charged_cells.saturating_sub(self.current_safe_cells(state));
+                let available_cells = charged_cells.saturating_sub(self.current_safe_cells(state));
 
                 Some(PlanetToExplorer::AvailableEnergyCellResponse {
                     available_cells,
@@ -316,41 +368,70 @@ impl PlanetAI for CiucAI {
             _ => None,
         }
     }
+}
+
+impl PlanetAI for CiucAI {
+    fn handle_explorer_msg(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+        msg: ExplorerToPlanet,
+    ) -> Option<PlanetToExplorer> {
+        let event = explorer_event(&msg);
+        let response = self.on_explorer_msg(state, generator, combinator, msg);
+        self.record_trace(state.id(), event, explorer_outcome(event, &response));
+        response
+    }
 
     fn handle_asteroid(
         &mut self,
         state: &mut PlanetState,
-        _generator: &Generator,
+        generator: &Generator,
         _combinator: &Combinator,
     ) -> Option<Rocket> {
-        CiucAI::log_event(
+        self.log(
+            LogCategory::Protocol,
             Some(Participant::new(ActorType::User, state.id())),
             None,
             EventType::MessageOrchestratorToPlanet,
             Channel::Info,
             [("message", "Asteroid received")],
         );
-        self.on_asteroid(state)
+        let rocket = self.on_asteroid(state, generator);
+        let outcome = match rocket {
+            Some(_) => SimOutcome::AsteroidDeflected,
+            None => SimOutcome::PlanetDestroyed,
+        };
+        self.record_trace(state.id(), SimEventKind::Asteroid, outcome);
+        rocket
     }
 
     fn handle_sunray(
         &mut self,
         state: &mut PlanetState,
-        _generator: &Generator,
+        generator: &Generator,
         _combinator: &Combinator,
         sunray: Sunray,
     ) {
-        CiucAI::log_event(
+        self.log(
+            LogCategory::Protocol,
             Some(Participant::new(ActorType::User, state.id())),
             None,
             EventType::MessageOrchestratorToPlanet,
             Channel::Info,
             [("message", "Sunray received")],
         );
-        let message = self.on_sunray(state, sunray);
+        let message = self.on_sunray(state, generator, sunray);
+        let outcome = match message {
+            Ok(_) => SimOutcome::SunrayCharged,
+            Err(_) => SimOutcome::SunrayWasted, // The only error is charging full cells
+        };
+        self.record_trace(state.id(), SimEventKind::Sunray, outcome);
         match message {
             Ok(_) => {
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Action,
                     Some(Participant::new(ActorType::Orchestrator, state.id())),
                     None,
                     EventType::InternalPlanetAction,
@@ -359,13 +440,14 @@ impl PlanetAI for CiucAI {
                 );
             }
             Err(e) => {
-                let channel = if e == "All cell are full of charge" {
+                let channel = if e == "All cells are full of charge" {
                     Channel::Info
                 } else {
                     Channel::Error
                 };
 
-                CiucAI::log_event(
+                self.log(
+                    LogCategory::Action,
                     Some(Participant::new(ActorType::User, state.id())),
                     None,
                     EventType::InternalPlanetAction,
@@ -382,7 +464,8 @@ impl PlanetAI for CiucAI {
         _generator: &Generator,
         _combinator: &Combinator,
     ) -> DummyPlanetState {
-        CiucAI::log_event(
+        self.log(
+            LogCategory::Protocol,
             Some(Participant::new(ActorType::User, state.id())),
             None,
             EventType::MessageOrchestratorToPlanet,
@@ -393,23 +476,85 @@ impl PlanetAI for CiucAI {
         state.to_dummy()
     }
 
+    fn on_explorer_arrival(
+        &mut self,
+        state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        explorer_id: u32,
+    ) {
+        self.explorer_arrived();
+        self.log(
+            LogCategory::Protocol,
+            Some(Participant::new(ActorType::User, state.id())),
+            Some(Participant::new(ActorType::Explorer, explorer_id)),
+            EventType::MessageOrchestratorToPlanet,
+            Channel::Info,
+            [("message", "Explorer arrived")],
+        );
+        self.record_trace(
+            state.id(),
+            SimEventKind::ExplorerArrival { explorer_id },
+            SimOutcome::ExplorerArrived,
+        );
+    }
+
+    fn on_explorer_departure(
+        &mut self,
+        state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        explorer_id: u32,
+    ) {
+        self.explorer_departed();
+        self.log(
+            LogCategory::Protocol,
+            Some(Participant::new(ActorType::User, state.id())),
+            Some(Participant::new(ActorType::Explorer, explorer_id)),
+            EventType::MessageOrchestratorToPlanet,
+            Channel::Info,
+            [("message", "Explorer departed")],
+        );
+        self.record_trace(
+            state.id(),
+            SimEventKind::ExplorerDeparture { explorer_id },
+            SimOutcome::ExplorerDeparted,
+        );
+    }
+
     fn on_start(&mut self, state: &PlanetState, _generator: &Generator, _combinator: &Combinator) {
-        CiucAI::log_event(
+        self.log(
+            LogCategory::Protocol,
             Some(Participant::new(ActorType::User, state.id())),
             None,
             EventType::InternalPlanetAction,
             Channel::Info,
             [("message", "Starting planet's AI")],
         );
+        for warning in self.config().warnings() {
+            self.log(
+                LogCategory::Protocol,
+                Some(Participant::new(ActorType::User, state.id())),
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Warning,
+                [("message", warning)],
+            );
+        }
+        self.resume_timers(state.id());
+        self.open_trace(state.id());
     }
 
     fn on_stop(&mut self, state: &PlanetState, _generator: &Generator, _combinator: &Combinator) {
-        CiucAI::log_event(
+        self.log(
+            LogCategory::Protocol,
             Some(Participant::new(ActorType::User, state.id())),
             None,
             EventType::InternalPlanetAction,
             Channel::Info,
             [("message", "Stopping planet's AI")],
         );
+        self.pause_timers();
+        self.save_state(state.id());
     }
 }
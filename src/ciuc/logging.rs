@@ -1,7 +1,140 @@
 use crate::CiucAI;
 use common_game::logging::Participant;
 use common_game::logging::{Channel, EventType, LogEvent};
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+/// Category of the events logged by the AI, used to configure verbosity and sampling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LogCategory {
+    Esteem,    // Updates of 'estimate_sunray_ms' and 'estimate_asteroid_ms'
+    SafeCells, // Decisions taken while computing the safe cells
+    State,     // Transitions between 'SafeState' and 'StatisticState'
+    Action,    // Rockets built, cells charged, asteroids deflected
+    Protocol,  // Messages received from or sent to the orchestrator and the explorers
+}
+
+/// Most detailed channel emitted for a category (warnings and errors are always emitted)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Verbosity {
+    Off,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Verbosity and sampling of a single category
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CategoryLogConfig {
+    /// Most detailed channel emitted
+    pub verbosity: Verbosity,
+    /// Emit only one event every `sample_every` (1 emits all of them)
+    pub sample_every: u32,
+    /// Maximum number of events emitted in a second, `None` means unlimited
+    pub max_per_second: Option<u32>,
+}
+
+impl Default for CategoryLogConfig {
+    fn default() -> Self {
+        CategoryLogConfig {
+            verbosity: Verbosity::Debug,
+            sample_every: 1,
+            max_per_second: None,
+        }
+    }
+}
+
+/// Logging configuration of the AI
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// Configuration used by the categories without an override
+    pub default: CategoryLogConfig,
+    /// Per-category configurations
+    pub overrides: HashMap<LogCategory, CategoryLogConfig>,
+}
+
+impl LogConfig {
+    ///Function that sets the configuration of a category
+    pub fn with_category(mut self, category: LogCategory, config: CategoryLogConfig) -> Self {
+        self.overrides.insert(category, config);
+        self
+    }
+
+    ///Function that returns the configuration of a category
+    pub fn category(&self, category: LogCategory) -> &CategoryLogConfig {
+        self.overrides.get(&category).unwrap_or(&self.default)
+    }
+}
+
+#[derive(Default)]
+struct SamplerState {
+    seen: u64,            // Events that passed the verbosity filter
+    window_start_ms: i64, // Start of the current one second window
+    window_emitted: u32,  // Events emitted in the current window
+    suppressed: u64,      // Events dropped since the last emitted one
+}
+
+/// Filter applying the logging configuration to the events of the AI
+pub(crate) struct CiucLogger {
+    config: LogConfig,
+    samplers: RefCell<HashMap<LogCategory, SamplerState>>,
+}
+
+fn channel_rank(channel: &Channel) -> Verbosity {
+    match channel {
+        Channel::Error | Channel::Warning => Verbosity::Off,
+        Channel::Info => Verbosity::Info,
+        Channel::Debug => Verbosity::Debug,
+        Channel::Trace => Verbosity::Trace,
+    }
+}
+
+impl CiucLogger {
+    pub(crate) fn new(config: LogConfig) -> Self {
+        CiucLogger {
+            config,
+            samplers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `None` if the event must be dropped, otherwise the number of events suppressed before it
+    fn admit(&self, category: LogCategory, channel: &Channel, now_ms: i64) -> Option<u64> {
+        // Warnings and errors are never filtered
+        if matches!(channel, Channel::Error | Channel::Warning) {
+            return Some(0);
+        }
+
+        let config = self.config.category(category);
+        if channel_rank(channel) > config.verbosity {
+            return None;
+        }
+
+        let mut samplers = self.samplers.borrow_mut();
+        let sampler = samplers.entry(category).or_default();
+        sampler.seen += 1;
+
+        if config.sample_every > 1 && !(sampler.seen - 1).is_multiple_of(config.sample_every as u64)
+        {
+            sampler.suppressed += 1;
+            return None;
+        }
+
+        if let Some(max) = config.max_per_second {
+            if now_ms - sampler.window_start_ms >= 1000 {
+                sampler.window_start_ms = now_ms;
+                sampler.window_emitted = 0;
+            }
+            if sampler.window_emitted >= max {
+                sampler.suppressed += 1;
+                return None;
+            }
+            sampler.window_emitted += 1;
+        }
+
+        Some(std::mem::take(&mut sampler.suppressed))
+    }
+}
 
 impl CiucAI {
     ///Function for logging
@@ -20,4 +153,29 @@ impl CiucAI {
         let event = LogEvent::new(sender, receiver, event_type, channel, payload);
         event.emit();
     }
+
+    ///Function for logging an event of a category, applying the verbosity and the sampling configured
+    pub(crate) fn log(
+        &self,
+        category: LogCategory,
+        sender: Option<Participant>,
+        receiver: Option<Participant>,
+        event_type: EventType,
+        channel: Channel,
+        payload: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) {
+        let Some(suppressed) = self.logger().admit(category, &channel, self.now()) else {
+            return;
+        };
+
+        let mut payload: BTreeMap<String, String> = payload
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        if suppressed > 0 {
+            payload.insert("suppressed".to_string(), suppressed.to_string());
+        }
+
+        CiucAI::log_event(sender, receiver, event_type, channel, payload);
+    }
 }
@@ -0,0 +1,342 @@
+use crate::ciuc::AIState;
+use crate::ciuc::clock::Clock;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+/// Upper bounds (in ms) of the buckets of the inter-arrival histograms
+const INTERVAL_BUCKETS_MS: [f64; 10] = [
+    50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0, 60000.0,
+];
+
+/// Histogram with fixed buckets, counts are cumulative like in Prometheus
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub bounds: Vec<f64>,
+    pub counts: Vec<u64>, // counts[i] = samples <= bounds[i]
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl Histogram {
+    ///Function that creates an empty histogram with the given bucket upper bounds
+    pub fn new(bounds: &[f64]) -> Self {
+        Histogram {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    ///Function that adds a sample to the histogram
+    pub fn observe(&mut self, sample: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if sample <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += sample;
+        self.count += 1;
+    }
+}
+
+/// Counters and histograms describing how the planet performs
+#[derive(Debug, Clone, PartialEq)]
+pub struct CiucMetrics {
+    pub sunrays_received: u64,
+    pub sunrays_wasted: u64, // Sunrays lost because all the cells were charged
+    pub sunrays_waste_avoided: u64, // Cells freed to make room for a sunray
+    pub asteroids_survived: u64,
+    pub asteroids_fatal: u64,
+    pub rockets_built: u64,
+    pub carbon_generated: BTreeMap<u32, u64>, // Per explorer
+    pub carbon_refused: BTreeMap<u32, u64>,   // Per explorer
+    pub time_in_safe_state_ms: i64,
+    pub time_in_statistic_state_ms: i64,
+    pub time_paused_ms: i64, // Time between a stop and the next start of the AI
+    pub sunray_interval_ms: Histogram,
+    pub asteroid_interval_ms: Histogram,
+    pub interval_samples_discarded: u64, // Negative intervals, the clock went backwards
+    pub interval_samples_clamped: u64,   // Intervals longer than 'max_interval_ms'
+    pub interval_samples_rejected: u64,  // Outliers kept out of the estimators
+    pub interval_samples_replaced: u64,  // Outliers replaced with the recent median
+    pub regime_changes: u64,             // Changes of the sunray or asteroid rate
+    pub carbon_stocked: u64,             // Carbon in the stockpile now
+    pub safe_cells: u32,                 // Safe cells of the last decision or answer
+    current_state: AIState,
+    state_entered_ms: i64,
+    paused_since: Option<i64>, // The time in a state doesn't count while the AI is stopped
+}
+
+impl CiucMetrics {
+    fn new(now: i64) -> Self {
+        CiucMetrics {
+            sunrays_received: 0,
+            sunrays_wasted: 0,
+            sunrays_waste_avoided: 0,
+            asteroids_survived: 0,
+            asteroids_fatal: 0,
+            rockets_built: 0,
+            carbon_generated: BTreeMap::new(),
+            carbon_refused: BTreeMap::new(),
+            time_in_safe_state_ms: 0,
+            time_in_statistic_state_ms: 0,
+            time_paused_ms: 0,
+            sunray_interval_ms: Histogram::new(&INTERVAL_BUCKETS_MS),
+            asteroid_interval_ms: Histogram::new(&INTERVAL_BUCKETS_MS),
+            interval_samples_discarded: 0,
+            interval_samples_clamped: 0,
+            interval_samples_rejected: 0,
+            interval_samples_replaced: 0,
+            regime_changes: 0,
+            carbon_stocked: 0,
+            safe_cells: 0,
+            current_state: AIState::SafeState,
+            state_entered_ms: now,
+            paused_since: None,
+        }
+    }
+
+    /// Adds the time spent in the current state up to `now`, unless the AI is stopped
+    fn close_state(&mut self, now: i64) {
+        if self.paused_since.is_none() {
+            let elapsed = (now - self.state_entered_ms).max(0);
+            match self.current_state {
+                AIState::SafeState => self.time_in_safe_state_ms += elapsed,
+                AIState::StatisticState => self.time_in_statistic_state_ms += elapsed,
+            }
+        }
+        self.state_entered_ms = now;
+    }
+
+    pub(crate) fn record_state(&mut self, state: AIState, now: i64) {
+        self.close_state(now);
+        self.current_state = state;
+    }
+
+    pub(crate) fn record_pause(&mut self, now: i64) {
+        self.close_state(now);
+        self.paused_since = Some(now);
+    }
+
+    pub(crate) fn record_resume(&mut self, now: i64) {
+        self.close_state(now);
+        if let Some(paused_since) = self.paused_since.take() {
+            self.time_paused_ms += (now - paused_since).max(0);
+        }
+    }
+}
+
+/// Shared handle to the metrics of an AI, readable while the planet is running
+#[derive(Debug, Clone)]
+pub struct MetricsHandle {
+    metrics: Arc<Mutex<CiucMetrics>>,
+    clock: Clock,
+}
+
+impl MetricsHandle {
+    pub(crate) fn new(clock: Clock) -> Self {
+        MetricsHandle {
+            metrics: Arc::new(Mutex::new(CiucMetrics::new(clock.now_ms()))),
+            clock,
+        }
+    }
+
+    pub(crate) fn record(&self, update: impl FnOnce(&mut CiucMetrics)) {
+        update(&mut self.metrics.lock().unwrap());
+    }
+
+    ///Function that returns a copy of the metrics, with the time in the current state counted up to now
+    pub fn snapshot(&self) -> CiucMetrics {
+        let mut snapshot = self.metrics.lock().unwrap().clone();
+        snapshot.close_state(self.clock.now_ms());
+        snapshot
+    }
+}
+
+fn write_counter(out: &mut String, name: &str, help: &str, labels: &str, value: impl ToString) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(out, "{name}{{{labels}}} {}", value.to_string());
+}
+
+fn write_gauge(out: &mut String, name: &str, help: &str, labels: &str, value: impl ToString) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let _ = writeln!(out, "{name}{{{labels}}} {}", value.to_string());
+}
+
+fn write_per_explorer(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    planet: &str,
+    values: &BTreeMap<u32, u64>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    for (explorer, value) in values {
+        let _ = writeln!(out, "{name}{{{planet},explorer=\"{explorer}\"}} {value}");
+    }
+}
+
+fn write_histogram(out: &mut String, name: &str, help: &str, labels: &str, h: &Histogram) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    for (bound, count) in h.bounds.iter().zip(h.counts.iter()) {
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {count}");
+    }
+    let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", h.count);
+    let _ = writeln!(out, "{name}_sum{{{labels}}} {}", h.sum);
+    let _ = writeln!(out, "{name}_count{{{labels}}} {}", h.count);
+}
+
+///Function that exports the metrics in the Prometheus text format
+pub fn to_prometheus(metrics: &CiucMetrics, planet_id: u32) -> String {
+    let labels = format!("planet=\"{planet_id}\"");
+    let mut out = String::new();
+
+    write_counter(
+        &mut out,
+        "ciuc_sunrays_received_total",
+        "Sunrays received by the planet",
+        &labels,
+        metrics.sunrays_received,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_sunrays_wasted_total",
+        "Sunrays lost because all the energy cells were charged",
+        &labels,
+        metrics.sunrays_wasted,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_sunrays_waste_avoided_total",
+        "Charged cells converted into rockets or carbon to make room for a sunray",
+        &labels,
+        metrics.sunrays_waste_avoided,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_asteroids_survived_total",
+        "Asteroids deflected with a rocket",
+        &labels,
+        metrics.asteroids_survived,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_asteroids_fatal_total",
+        "Asteroids that hit the planet without a rocket",
+        &labels,
+        metrics.asteroids_fatal,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_rockets_built_total",
+        "Rockets built",
+        &labels,
+        metrics.rockets_built,
+    );
+    write_per_explorer(
+        &mut out,
+        "ciuc_carbon_generated_total",
+        "Carbon sent to each explorer",
+        &labels,
+        &metrics.carbon_generated,
+    );
+    write_per_explorer(
+        &mut out,
+        "ciuc_carbon_refused_total",
+        "Carbon requests refused to each explorer",
+        &labels,
+        &metrics.carbon_refused,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_interval_samples_discarded_total",
+        "Negative intervals between events discarded because the clock went backwards",
+        &labels,
+        metrics.interval_samples_discarded,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_interval_samples_clamped_total",
+        "Intervals between events clamped to the longest accepted one",
+        &labels,
+        metrics.interval_samples_clamped,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_interval_samples_rejected_total",
+        "Intervals between events kept out of the estimators as outliers",
+        &labels,
+        metrics.interval_samples_rejected,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_interval_samples_replaced_total",
+        "Intervals between events replaced with the recent median as outliers",
+        &labels,
+        metrics.interval_samples_replaced,
+    );
+    write_counter(
+        &mut out,
+        "ciuc_regime_changes_total",
+        "Changes of the sunray or asteroid rate, each restarting an estimate",
+        &labels,
+        metrics.regime_changes,
+    );
+    write_gauge(
+        &mut out,
+        "ciuc_carbon_stocked",
+        "Carbon in the stockpile, ready for the explorers",
+        &labels,
+        metrics.carbon_stocked,
+    );
+    write_gauge(
+        &mut out,
+        "ciuc_safe_cells",
+        "Charged cells preserved by the last decision or answer",
+        &labels,
+        metrics.safe_cells,
+    );
+
+    let name = "ciuc_time_in_state_ms_total";
+    let _ = writeln!(out, "# HELP {name} Time spent by the AI in each state");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(
+        out,
+        "{name}{{{labels},state=\"safe\"}} {}",
+        metrics.time_in_safe_state_ms
+    );
+    let _ = writeln!(
+        out,
+        "{name}{{{labels},state=\"statistic\"}} {}",
+        metrics.time_in_statistic_state_ms
+    );
+    write_counter(
+        &mut out,
+        "ciuc_time_paused_ms_total",
+        "Time spent by the AI stopped, between a stop and the next start",
+        &labels,
+        metrics.time_paused_ms,
+    );
+
+    write_histogram(
+        &mut out,
+        "ciuc_sunray_interval_ms",
+        "Time between two consecutive sunrays",
+        &labels,
+        &metrics.sunray_interval_ms,
+    );
+    write_histogram(
+        &mut out,
+        "ciuc_asteroid_interval_ms",
+        "Time between two consecutive asteroids",
+        &labels,
+        &metrics.asteroid_interval_ms,
+    );
+    out
+}
@@ -1,11 +1,48 @@
 mod actions;
+mod adaptive;
 mod carbon;
 mod ciuc_ai;
+mod clock;
+mod config;
 mod create_planet;
+mod decision;
 mod esteem;
+mod events;
 mod handlers;
 mod logging;
+mod metrics;
+mod outlier;
+mod pause;
+mod periodicity;
+mod persistence;
+mod policy;
+mod q_learning;
+mod regime;
+mod saturation;
+pub mod simulation;
+mod stockpile;
+mod strategy;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod trace;
 
 pub use ciuc_ai::{AIState, CiucAI};
-pub use create_planet::create_planet;
-pub use esteem::update_ema;
+pub use adaptive::AdaptiveMarginConfig;
+pub use carbon::{SafeCells, SafeCellsAdjustment, SafeCellsConfig, SafeCellsStage};
+pub use clock::Clock;
+pub use config::CiucConfig;
+pub use create_planet::{
+    create_planet, create_planet_from_state, create_planet_with_ai, create_planet_with_config,
+};
+pub use decision::{CarbonDecision, DecisionLog};
+pub use esteem::{Prior, update_ema};
+pub use logging::{CategoryLogConfig, LogCategory, LogConfig, Verbosity};
+pub use metrics::{CiucMetrics, Histogram, MetricsHandle, to_prometheus};
+pub use outlier::{OutlierAction, OutlierConfig};
+pub use periodicity::PeriodicityConfig;
+pub use persistence::CiucAIState;
+pub use policy::{Observation, Policy, PolicyFeedback};
+pub use q_learning::{QLearningConfig, QLearningPolicy, QTable, QTableHandle};
+pub use regime::RegimeChangeConfig;
+pub use strategy::{CiucStrategy, DefaultStrategy, RocketTrigger};
+pub use trace::{TraceRecord, read_trace};
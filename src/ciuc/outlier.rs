@@ -0,0 +1,159 @@
+use crate::CiucAI;
+use crate::ciuc::logging::LogCategory;
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Scale of the median absolute deviation to estimate the standard deviation of normal samples
+const MAD_SCALE: f64 = 1.4826;
+
+/// What is done with an interval too far from the recent ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutlierAction {
+    Reject,            // The interval doesn't reach the estimators
+    ReplaceWithMedian, // Hampel filter: the median of the recent intervals is used instead
+}
+
+/// Detection of the intervals far from the recent ones (a pause of the orchestrator, a lost event),
+/// using the median absolute deviation (MAD) of a window of recent intervals
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutlierConfig {
+    /// Number of recent intervals the median is computed on
+    pub window: usize,
+    /// Intervals needed in the window before any is considered an outlier
+    pub min_samples: usize,
+    /// Distance from the median, in scaled MADs, beyond which an interval is an outlier
+    pub threshold: f64,
+    /// Smallest spread, as a fraction of the median, so that a perfectly regular schedule doesn't reject every jitter
+    pub min_spread: f64,
+    pub action: OutlierAction,
+}
+
+impl Default for OutlierConfig {
+    fn default() -> Self {
+        OutlierConfig {
+            window: 16,
+            min_samples: 5,
+            threshold: 3.0,
+            min_spread: 0.1,
+            action: OutlierAction::Reject,
+        }
+    }
+}
+
+/// Outcome of the outlier check of an interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OutlierVerdict {
+    Inlier,
+    Outlier { median: f64 },
+}
+
+/// Recent intervals of one kind of event. Outliers are kept in the window too,
+/// so that a lasting change of the schedule is accepted once it becomes the majority
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OutlierFilter {
+    window: VecDeque<f64>,
+}
+
+///Function that returns the median of non-empty values
+fn median_of(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+impl OutlierFilter {
+    ///Function that checks an interval against the previous ones, then adds it to the window
+    pub(crate) fn check(&mut self, sample: f64, config: &OutlierConfig) -> OutlierVerdict {
+        let verdict = if !self.window.is_empty() && self.window.len() >= config.min_samples {
+            let mut values: Vec<f64> = self.window.iter().copied().collect();
+            let median = median_of(&mut values);
+            let mut deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+            let spread = (MAD_SCALE * median_of(&mut deviations)).max(config.min_spread * median);
+            if (sample - median).abs() > config.threshold * spread {
+                OutlierVerdict::Outlier { median }
+            } else {
+                OutlierVerdict::Inlier
+            }
+        } else {
+            OutlierVerdict::Inlier
+        };
+        self.window.push_back(sample);
+        while self.window.len() > config.window.max(1) {
+            self.window.pop_front();
+        }
+        verdict
+    }
+}
+
+impl CiucAI {
+    ///Function that returns the sunray interval to give to the estimators, `None` if it is rejected
+    pub(crate) fn filter_sunray_sample(&mut self, sample: f64, id: u32) -> Option<f64> {
+        let Some(config) = self.config().outliers else {
+            return Some(sample);
+        };
+        let verdict = self.sunray_outliers_mut().check(sample, &config);
+        self.apply_outlier_verdict(sample, verdict, &config, id, "sunray")
+    }
+
+    ///Function that returns the asteroid interval to give to the estimators, `None` if it is rejected
+    pub(crate) fn filter_asteroid_sample(&mut self, sample: f64, id: u32) -> Option<f64> {
+        let Some(config) = self.config().outliers else {
+            return Some(sample);
+        };
+        let verdict = self.asteroid_outliers_mut().check(sample, &config);
+        self.apply_outlier_verdict(sample, verdict, &config, id, "asteroid")
+    }
+
+    fn apply_outlier_verdict(
+        &self,
+        sample: f64,
+        verdict: OutlierVerdict,
+        config: &OutlierConfig,
+        id: u32,
+        event: &str,
+    ) -> Option<f64> {
+        let OutlierVerdict::Outlier { median } = verdict else {
+            return Some(sample);
+        };
+        let (result, message) = match config.action {
+            OutlierAction::Reject => {
+                self.metrics_handle()
+                    .record(|m| m.interval_samples_rejected += 1);
+                (
+                    None,
+                    format!(
+                        "Rejected a {} interval of {}ms, the recent median is {}ms",
+                        event, sample, median
+                    ),
+                )
+            }
+            OutlierAction::ReplaceWithMedian => {
+                self.metrics_handle()
+                    .record(|m| m.interval_samples_replaced += 1);
+                (
+                    Some(median),
+                    format!(
+                        "Replaced a {} interval of {}ms with the recent median {}ms",
+                        event, sample, median
+                    ),
+                )
+            }
+        };
+        self.log(
+            LogCategory::Esteem,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Warning,
+            [("message", message)],
+        );
+        result
+    }
+}
@@ -0,0 +1,54 @@
+use crate::CiucAI;
+use crate::ciuc::logging::LogCategory;
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+
+impl CiucAI {
+    ///Function for freezing the timers when the AI is stopped
+    pub(crate) fn pause_timers(&mut self) {
+        let now = self.now();
+        self.set_paused_at(Some(now));
+        self.metrics_handle().record(|m| m.record_pause(now));
+    }
+
+    ///Function for resuming the timers when the AI is started again: the times of the last events are moved
+    ///forward by the pause, so that every interval only counts the time in which the AI was running
+    pub(crate) fn resume_timers(&mut self, id: u32) {
+        let Some(paused_at) = self.paused_at() else {
+            return; // First start
+        };
+        self.set_paused_at(None);
+        let now = self.now();
+        let pause = (now - paused_at).max(0);
+        self.metrics_handle().record(|m| m.record_resume(now));
+
+        if let Some(t) = self.last_time_sunray() {
+            self.set_last_time_sunray(t + pause);
+        }
+        if let Some(t) = self.last_time_asteroid() {
+            self.set_last_time_asteroid(t + pause);
+        }
+        if let Some(t) = self.margin_changed_ms() {
+            self.set_margin_changed_ms(t + pause);
+        }
+        self.log(
+            LogCategory::Esteem,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Info,
+            [(
+                "message",
+                format!("Resumed the timers after a pause of {}ms", pause),
+            )],
+        );
+
+        if self
+            .config()
+            .pause_reset_ms
+            .is_some_and(|reset_ms| pause >= reset_ms)
+        {
+            self.restart_estimation(id, "after a long pause");
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::CiucAI;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Detection of events sent on a fixed timer: when the recent intervals barely vary, the next arrival is
+/// predicted from the last one, and the safe cells use the time until it instead of fractions of the mean interval
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeriodicityConfig {
+    /// Number of recent intervals the period is computed on
+    pub window: usize,
+    /// Intervals needed before a schedule can be considered periodic
+    pub min_samples: usize,
+    /// Highest standard deviation of the intervals, as a fraction of their mean, of a periodic schedule
+    pub max_jitter: f64,
+    /// A sunray predicted within this time is imminent
+    pub sunray_lead_ms: i64,
+    /// An asteroid predicted within this time is near
+    pub asteroid_lead_ms: i64,
+}
+
+impl Default for PeriodicityConfig {
+    fn default() -> Self {
+        PeriodicityConfig {
+            window: 8,
+            min_samples: 4,
+            max_jitter: 0.05,
+            sunray_lead_ms: 250,
+            asteroid_lead_ms: 1_000,
+        }
+    }
+}
+
+/// Recent intervals of one kind of event, to find its period
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PeriodTracker {
+    intervals: VecDeque<f64>,
+}
+
+impl PeriodTracker {
+    ///Function that adds an interval, forgetting the oldest one beyond the window
+    pub(crate) fn observe(&mut self, sample: f64, config: &PeriodicityConfig) {
+        self.intervals.push_back(sample);
+        while self.intervals.len() > config.window.max(1) {
+            self.intervals.pop_front();
+        }
+    }
+
+    ///Function that returns the period of the events, `None` if the recent intervals are not regular enough
+    pub(crate) fn period(&self, config: &PeriodicityConfig) -> Option<f64> {
+        let n = self.intervals.len();
+        if n == 0 || n < config.min_samples {
+            return None;
+        }
+        let mean = self.intervals.iter().sum::<f64>() / n as f64;
+        let variance = self
+            .intervals
+            .iter()
+            .map(|v| (v - mean).powi(2))
+            .sum::<f64>()
+            / n as f64;
+        (mean > 0.0 && variance.sqrt() <= config.max_jitter * mean).then_some(mean)
+    }
+}
+
+///Function that returns the time until the event following the one at 'last' (negative if it is late).
+///The last arrival sets the phase: a schedule late by more than a period is considered lost
+fn until_next(last: Option<i64>, period: Option<f64>, now: i64) -> Option<i64> {
+    let (last, period) = (last?, period?);
+    let next = last + period.round() as i64;
+    ((now - next) as f64 <= period).then_some(next - now)
+}
+
+impl CiucAI {
+    ///Function for adding a sunray interval to the detection of the period, if enabled
+    pub(crate) fn observe_sunray_period(&mut self, sample: f64) {
+        if let Some(config) = self.config().periodicity {
+            self.sunray_period_mut().observe(sample, &config);
+        }
+    }
+
+    ///Function for adding an asteroid interval to the detection of the period, if enabled
+    pub(crate) fn observe_asteroid_period(&mut self, sample: f64) {
+        if let Some(config) = self.config().periodicity {
+            self.asteroid_period_mut().observe(sample, &config);
+        }
+    }
+
+    ///Function that returns the time until the next sunray, `None` if the sunrays are not periodic
+    pub(crate) fn until_next_sunray_ms(&self, now: i64) -> Option<i64> {
+        let config = self.config().periodicity?;
+        until_next(
+            self.last_time_sunray(),
+            self.sunray_period().period(&config),
+            now,
+        )
+    }
+
+    ///Function that returns the time until the next asteroid, `None` if the asteroids are not periodic
+    pub(crate) fn until_next_asteroid_ms(&self, now: i64) -> Option<i64> {
+        let config = self.config().periodicity?;
+        until_next(
+            self.last_time_asteroid(),
+            self.asteroid_period().period(&config),
+            now,
+        )
+    }
+}
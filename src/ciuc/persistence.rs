@@ -0,0 +1,106 @@
+use crate::CiucAI;
+use crate::ciuc::AIState;
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::logging::LogCategory;
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// What the AI has learned, saved to warm-start a new AI after a restart.
+/// The times of the last events are not saved: the interval across a restart is meaningless.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CiucAIState {
+    pub state: AIState,
+    pub count_sunrays: u32,
+    pub count_asteroids: u32,
+    pub estimate_sunray_ms: f64,
+    pub estimate_asteroid_ms: f64,
+    /// Margin learned by the online adaptation, 0 in states saved before it existed
+    #[serde(default)]
+    pub safe_cells_margin: i32,
+    pub config: CiucConfig,
+}
+
+impl CiucAIState {
+    ///Function that encodes the state as JSON bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec_pretty(self).map_err(|e| format!("Can't encode the AI state: {}", e))
+    }
+
+    ///Function that decodes a state encoded by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(bytes).map_err(|e| format!("Can't decode the AI state: {}", e))
+    }
+
+    ///Function for saving the state to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()?)
+            .map_err(|e| format!("Can't write the AI state to {}: {}", path.display(), e))
+    }
+
+    ///Function for loading a state saved by `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Can't read the AI state from {}: {}", path.display(), e))?;
+        CiucAIState::from_bytes(&bytes)
+    }
+}
+
+impl CiucAI {
+    ///Function that returns what the AI has learned so far
+    pub fn export_state(&self) -> CiucAIState {
+        let mut config = self.config().clone();
+        // The learned table replaces the starting one
+        if let (Some(q_learning), Some(table)) = (&mut config.q_learning, self.q_table()) {
+            q_learning.table = table.snapshot();
+        }
+        CiucAIState {
+            state: *self.state(),
+            count_sunrays: self.count_sunrays(),
+            count_asteroids: self.count_asteroids(),
+            estimate_sunray_ms: self.estimate_sunray_ms(),
+            estimate_asteroid_ms: self.estimate_asteroid_ms(),
+            safe_cells_margin: self.safe_cells_margin(),
+            config,
+        }
+    }
+
+    ///Function that creates an AI resuming from a saved state
+    pub fn from_state(saved: CiucAIState) -> Self {
+        let mut ai = CiucAI::with_config(saved.config);
+        ai.set_state(saved.state);
+        ai.set_count_sunrays(saved.count_sunrays);
+        ai.set_count_asteroids(saved.count_asteroids);
+        ai.set_estimate_sunray_ms(saved.estimate_sunray_ms);
+        ai.set_estimate_asteroid_ms(saved.estimate_asteroid_ms);
+        ai.set_safe_cells_margin(saved.safe_cells_margin);
+        ai
+    }
+
+    ///Function for saving the learned state to the configured file, if any
+    pub(crate) fn save_state(&self, id: u32) {
+        let Some(path) = &self.config().state_path else {
+            return;
+        };
+
+        let (channel, message) = match self.export_state().save(path) {
+            Ok(()) => (
+                Channel::Info,
+                format!("Saved the AI state to {}", path.display()),
+            ),
+            Err(e) => (Channel::Warning, e),
+        };
+        self.log(
+            LogCategory::State,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            channel,
+            [("message", message)],
+        );
+    }
+}
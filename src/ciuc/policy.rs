@@ -0,0 +1,110 @@
+use crate::CiucAI;
+use crate::ciuc::AIState;
+use crate::ciuc::carbon::SafeCells;
+use common_game::components::planet::PlanetState;
+
+/// What a policy or a strategy sees when deciding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observation {
+    pub state: AIState,
+    pub charged_cells: u32,
+    pub cell_count: u32,
+    pub has_rocket: bool,
+    /// Explorers on the planet
+    pub explorers: usize,
+    /// `None` if no sunray has been received yet
+    pub since_last_sunray_ms: Option<i64>,
+    /// `None` if no asteroid has been received yet
+    pub since_last_asteroid_ms: Option<i64>,
+    /// Time until the predicted sunray (negative if late), `None` if the sunrays are not periodic
+    pub until_next_sunray_ms: Option<i64>,
+    /// Time until the predicted asteroid (negative if late), `None` if the asteroids are not periodic
+    pub until_next_asteroid_ms: Option<i64>,
+    pub estimate_sunray_ms: f64,
+    pub estimate_asteroid_ms: f64,
+    /// Intervals observed so far, priors included
+    pub count_sunrays: u32,
+    pub count_asteroids: u32,
+}
+
+/// Outcome reported to the policy, so that it can learn from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyFeedback {
+    CarbonGranted,
+    CarbonRefused,
+    AsteroidDeflected,
+    PlanetDestroyed, // Last feedback of the planet
+}
+
+/// Replaces the hand-written thresholds in choosing how many charged cells are preserved
+pub trait Policy: Send {
+    /// Safe cells the policy keeps in the observed situation, without exploring
+    fn safe_cells(&self, observation: &Observation) -> u32;
+
+    /// Safe cells used to answer a carbon request: a learning policy may explore and remember its choice
+    fn decide(&mut self, observation: &Observation) -> u32 {
+        self.safe_cells(observation)
+    }
+
+    /// Called after a carbon request or an asteroid (does nothing by default)
+    fn feedback(&mut self, _feedback: PolicyFeedback) {}
+}
+
+impl CiucAI {
+    ///Function that returns what the policy sees at time 'now'
+    pub(crate) fn observation(&self, planet_state: &PlanetState, now: i64) -> Observation {
+        let since = |last: Option<i64>| last.map(|t| (now - t).max(0)); // A clock gone backwards counts as no time
+        Observation {
+            state: *self.state(),
+            charged_cells: planet_state.cells_iter().filter(|c| c.is_charged()).count() as u32,
+            cell_count: planet_state.cells_count() as u32,
+            has_rocket: planet_state.has_rocket(),
+            explorers: self.number_explorers(),
+            since_last_sunray_ms: since(self.last_time_sunray()),
+            since_last_asteroid_ms: since(self.last_time_asteroid()),
+            until_next_sunray_ms: self.until_next_sunray_ms(now),
+            until_next_asteroid_ms: self.until_next_asteroid_ms(now),
+            estimate_sunray_ms: self.estimate_sunray_ms(),
+            estimate_asteroid_ms: self.estimate_asteroid_ms(),
+            count_sunrays: self.count_sunrays(),
+            count_asteroids: self.count_asteroids(),
+        }
+    }
+
+    ///Function that asks the policy, if any, the safe cells for a carbon request
+    pub(crate) fn decide_safe_cells(
+        &mut self,
+        planet_state: &mut PlanetState,
+        now: i64,
+    ) -> SafeCells {
+        let observation = self.observation(planet_state, now);
+        match self.policy_mut().map(|policy| policy.decide(&observation)) {
+            Some(value) => {
+                let safe_cells = self.bound_policy_safe_cells(value, &observation);
+                self.finish_safe_cells(safe_cells, planet_state.id())
+            }
+            None => self.safe_cells_breakdown(planet_state, now),
+        }
+    }
+
+    ///Function that starts the pipeline from the value of the policy:
+    ///in 'SafeState' it never keeps less than the threshold of the state
+    pub(crate) fn bound_policy_safe_cells(
+        &self,
+        value: u32,
+        observation: &Observation,
+    ) -> SafeCells {
+        let value = match observation.state {
+            AIState::SafeState => value.max(self.config().safe_cells.safe_cells),
+            AIState::StatisticState => value,
+        };
+        SafeCells::new(value, observation.cell_count)
+    }
+
+    ///Function for reporting an outcome to the policy, if any
+    pub(crate) fn policy_feedback(&mut self, feedback: PolicyFeedback) {
+        if let Some(policy) = self.policy_mut() {
+            policy.feedback(feedback);
+        }
+    }
+}
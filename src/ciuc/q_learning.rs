@@ -0,0 +1,224 @@
+use crate::ciuc::policy::{Observation, Policy, PolicyFeedback};
+use crate::ciuc::simulation::SimRng;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Buckets of the time since the last event, relative to its estimated interval:
+/// unknown, less than half, less than the whole interval, overdue
+const TIME_BUCKETS: usize = 4;
+/// Charged cells from 0 to 5
+const CHARGE_LEVELS: usize = 6;
+/// Actions: keep from 0 to 5 safe cells
+const ACTIONS: usize = 6;
+const STATES: usize = CHARGE_LEVELS * 2 * TIME_BUCKETS * TIME_BUCKETS;
+
+/// Values of each (state, action) pair of the Q-learning policy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedQTable")]
+pub struct QTable {
+    values: Vec<f64>, // STATES * ACTIONS, row-major by state
+}
+
+/// Table as saved, checked before becoming a 'QTable'
+#[derive(Deserialize)]
+struct SavedQTable {
+    values: Vec<f64>,
+}
+
+impl TryFrom<SavedQTable> for QTable {
+    type Error = String;
+
+    ///Function that refuses a table of the wrong size, e.g. saved by a version with other states or actions
+    fn try_from(saved: SavedQTable) -> Result<Self, String> {
+        if saved.values.len() != STATES * ACTIONS {
+            return Err(format!(
+                "The Q-table has {} values, expected {} ({} states, {} actions)",
+                saved.values.len(),
+                STATES * ACTIONS,
+                STATES,
+                ACTIONS
+            ));
+        }
+        Ok(QTable {
+            values: saved.values,
+        })
+    }
+}
+
+impl Default for QTable {
+    fn default() -> Self {
+        QTable {
+            values: vec![0.0; STATES * ACTIONS],
+        }
+    }
+}
+
+impl QTable {
+    fn row(&self, state: usize) -> &[f64] {
+        &self.values[state * ACTIONS..(state + 1) * ACTIONS]
+    }
+
+    ///Function that returns the best action, ties go to the safest one
+    fn best_action(&self, state: usize) -> usize {
+        let row = self.row(state);
+        // 'max_by' returns the last of equal maxima, the action keeping the most cells
+        (0..ACTIONS)
+            .max_by(|&a, &b| row[a].total_cmp(&row[b]))
+            .unwrap_or(ACTIONS - 1)
+    }
+
+    fn best_value(&self, state: usize) -> f64 {
+        self.row(state)[self.best_action(state)]
+    }
+
+    ///Function that returns the value of an action in a state
+    pub fn value(&self, observation: &Observation, safe_cells: u32) -> f64 {
+        self.row(state_index(observation))[(safe_cells as usize).min(ACTIONS - 1)]
+    }
+
+    ///Function that returns true if nothing has been learned yet
+    pub fn is_empty(&self) -> bool {
+        self.values.iter().all(|v| *v == 0.0)
+    }
+}
+
+/// Shared handle to the table of a Q-learning policy, readable after the AI is moved into the planet
+#[derive(Debug, Clone)]
+pub struct QTableHandle {
+    table: Arc<Mutex<QTable>>,
+}
+
+impl QTableHandle {
+    ///Function that returns a copy of the table
+    pub fn snapshot(&self) -> QTable {
+        self.table.lock().unwrap().clone()
+    }
+}
+
+/// Parameters and starting table of the Q-learning policy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QLearningConfig {
+    /// Weight of a new experience (0 stops learning)
+    pub learning_rate: f64,
+    /// Weight of the future rewards
+    pub discount: f64,
+    /// Probability of trying a random action on a carbon request (0 when deployed)
+    pub exploration: f64,
+    pub seed: u64,
+    pub carbon_reward: f64,
+    pub destroyed_penalty: f64,
+    pub table: QTable,
+}
+
+impl Default for QLearningConfig {
+    fn default() -> Self {
+        QLearningConfig {
+            learning_rate: 0.1,
+            discount: 0.9,
+            exploration: 0.1,
+            seed: 0,
+            carbon_reward: 1.0,
+            destroyed_penalty: 100.0,
+            table: QTable::default(),
+        }
+    }
+}
+
+///Function that returns the bucket of the time since an event
+fn time_bucket(since_ms: Option<i64>, estimate_ms: f64) -> usize {
+    match since_ms {
+        Some(since) if estimate_ms > 0.0 => {
+            let ratio = since as f64 / estimate_ms;
+            if ratio < 0.5 {
+                1
+            } else if ratio < 1.0 {
+                2
+            } else {
+                3
+            }
+        }
+        _ => 0,
+    }
+}
+
+fn state_index(o: &Observation) -> usize {
+    let charged = (o.charged_cells as usize).min(CHARGE_LEVELS - 1);
+    let sunray = time_bucket(o.since_last_sunray_ms, o.estimate_sunray_ms);
+    let asteroid = time_bucket(o.since_last_asteroid_ms, o.estimate_asteroid_ms);
+    ((charged * 2 + o.has_rocket as usize) * TIME_BUCKETS + sunray) * TIME_BUCKETS + asteroid
+}
+
+/// Tabular Q-learning policy: the safe cells are the action, the state is the charge,
+/// the rocket and how long ago the last sunray and asteroid arrived
+pub struct QLearningPolicy {
+    config: QLearningConfig,
+    table: Arc<Mutex<QTable>>,
+    rng: SimRng,
+    pending: Option<(usize, usize, f64)>, // Last (state, action) and the reward collected since
+}
+
+impl QLearningPolicy {
+    ///Function that creates the policy, starting from the table in the configuration
+    pub fn new(config: QLearningConfig) -> Self {
+        QLearningPolicy {
+            table: Arc::new(Mutex::new(config.table.clone())),
+            rng: SimRng::new(config.seed),
+            pending: None,
+            config,
+        }
+    }
+
+    ///Function that returns a handle to the table being learned
+    pub fn table(&self) -> QTableHandle {
+        QTableHandle {
+            table: self.table.clone(),
+        }
+    }
+
+    ///Function for updating the last (state, action), 'next' is `None` when the episode ended
+    fn update(&mut self, next: Option<usize>) {
+        let Some((state, action, reward)) = self.pending.take() else {
+            return;
+        };
+        let mut table = self.table.lock().unwrap();
+        let future = next.map_or(0.0, |s| self.config.discount * table.best_value(s));
+        let q = &mut table.values[state * ACTIONS + action];
+        *q += self.config.learning_rate * (reward + future - *q);
+    }
+}
+
+impl Policy for QLearningPolicy {
+    fn safe_cells(&self, observation: &Observation) -> u32 {
+        self.table
+            .lock()
+            .unwrap()
+            .best_action(state_index(observation)) as u32
+    }
+
+    fn decide(&mut self, observation: &Observation) -> u32 {
+        let state = state_index(observation);
+        self.update(Some(state));
+        let action = if self.rng.next_f64() < self.config.exploration {
+            self.rng.range_i64(0, ACTIONS as i64 - 1) as usize
+        } else {
+            self.table.lock().unwrap().best_action(state)
+        };
+        self.pending = Some((state, action, 0.0));
+        action as u32
+    }
+
+    fn feedback(&mut self, feedback: PolicyFeedback) {
+        let reward = match feedback {
+            PolicyFeedback::CarbonGranted => self.config.carbon_reward,
+            PolicyFeedback::PlanetDestroyed => -self.config.destroyed_penalty,
+            PolicyFeedback::CarbonRefused | PolicyFeedback::AsteroidDeflected => 0.0,
+        };
+        if let Some((_, _, collected)) = &mut self.pending {
+            *collected += reward;
+        }
+        if feedback == PolicyFeedback::PlanetDestroyed {
+            self.update(None);
+        }
+    }
+}
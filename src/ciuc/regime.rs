@@ -0,0 +1,137 @@
+use crate::CiucAI;
+use crate::ciuc::AIState;
+use crate::ciuc::logging::LogCategory;
+use crate::ciuc::outlier::OutlierFilter;
+use crate::ciuc::periodicity::PeriodTracker;
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+use serde::{Deserialize, Serialize};
+
+/// Detection of a lasting change of an event rate with a two-sided Page-Hinkley test on the intervals.
+/// The intervals are measured relative to the mean of the current regime, so the parameters don't depend on the schedule
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegimeChangeConfig {
+    /// Relative deviation from the mean tolerated without accumulating evidence of a change
+    pub tolerance: f64,
+    /// Evidence (sum of relative deviations) needed to signal a change
+    pub threshold: f64,
+    /// Intervals of the current regime needed before a change can be signaled
+    pub min_samples: u32,
+}
+
+impl Default for RegimeChangeConfig {
+    fn default() -> Self {
+        RegimeChangeConfig {
+            tolerance: 0.1,
+            threshold: 2.0,
+            min_samples: 5,
+        }
+    }
+}
+
+/// Direction of a change of the event rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RegimeShift {
+    Faster, // Shorter intervals
+    Slower, // Longer intervals
+}
+
+/// State of the Page-Hinkley test on one kind of event, since the start of the current regime
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PageHinkley {
+    count: u32,
+    mean: f64,
+    longer: f64, // Cumulative deviation looking for longer intervals, and its minimum
+    longer_min: f64,
+    shorter: f64, // Cumulative deviation looking for shorter intervals, and its maximum
+    shorter_max: f64,
+}
+
+impl PageHinkley {
+    ///Function that adds an interval to the test, returns the change if one is detected (and a new regime starts)
+    pub(crate) fn observe(
+        &mut self,
+        sample: f64,
+        config: &RegimeChangeConfig,
+    ) -> Option<RegimeShift> {
+        self.count += 1;
+        self.mean += (sample - self.mean) / self.count as f64;
+        if self.mean <= 0.0 {
+            return None;
+        }
+        // A single interval moves the test by at most one mean, so an isolated outlier can't signal a change
+        let deviation = (sample / self.mean - 1.0).min(1.0);
+        self.longer += deviation - config.tolerance;
+        self.longer_min = self.longer_min.min(self.longer);
+        self.shorter += deviation + config.tolerance;
+        self.shorter_max = self.shorter_max.max(self.shorter);
+
+        if self.count < config.min_samples {
+            return None;
+        }
+        let shift = if self.longer - self.longer_min > config.threshold {
+            RegimeShift::Slower
+        } else if self.shorter_max - self.shorter > config.threshold {
+            RegimeShift::Faster
+        } else {
+            return None;
+        };
+        *self = PageHinkley::default();
+        Some(shift)
+    }
+}
+
+impl CiucAI {
+    ///Function for checking the sunray intervals for a change of rate: on a change the sunray estimate restarts
+    pub(crate) fn detect_sunray_regime_change(&mut self, sample: f64, id: u32) {
+        let Some(config) = self.config().regime_change else {
+            return;
+        };
+        if let Some(shift) = self.sunray_regime_mut().observe(sample, &config) {
+            self.set_count_sunrays(0);
+            *self.sunray_outliers_mut() = OutlierFilter::default();
+            *self.sunray_period_mut() = PeriodTracker::default();
+            self.enter_new_regime(shift, id, "sunray");
+        }
+    }
+
+    ///Function for checking the asteroid intervals for a change of rate: on a change the asteroid estimate restarts
+    pub(crate) fn detect_asteroid_regime_change(&mut self, sample: f64, id: u32) {
+        let Some(config) = self.config().regime_change else {
+            return;
+        };
+        if let Some(shift) = self.asteroid_regime_mut().observe(sample, &config) {
+            self.set_count_asteroids(0);
+            *self.asteroid_outliers_mut() = OutlierFilter::default();
+            *self.asteroid_period_mut() = PeriodTracker::default();
+            self.enter_new_regime(shift, id, "asteroid");
+        }
+    }
+
+    ///Function for going back to 'SafeState' until the new regime is estimated
+    fn enter_new_regime(&mut self, shift: RegimeShift, id: u32, event: &str) {
+        self.metrics_handle().record(|m| m.regime_changes += 1);
+        if *self.state() != AIState::SafeState {
+            self.set_state(AIState::SafeState);
+        }
+        let direction = match shift {
+            RegimeShift::Faster => "more frequent",
+            RegimeShift::Slower => "less frequent",
+        };
+        self.log(
+            LogCategory::State,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Info,
+            [(
+                "message",
+                format!(
+                    "The {}s became {}, restarting their estimate in safe state",
+                    event, direction
+                ),
+            )],
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::CiucAI;
+use crate::ciuc::logging::LogCategory;
+use crate::ciuc::strategy::RocketTrigger;
+use common_game::components::planet::PlanetState;
+use common_game::components::resource::Generator;
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+
+/// Fraction of the estimated sunray interval after which the next sunray is expected to be wasted on full cells
+const SATURATION_IMMINENT_THRESHOLD: f64 = 0.75;
+
+impl CiucAI {
+    ///Function that returns true if every energy cell is charged
+    pub(crate) fn is_saturated(&self, planet_state: &PlanetState) -> bool {
+        planet_state.cells_iter().all(|c| c.is_charged())
+    }
+
+    ///Function that returns true if the cells are full and the next sunray is expected soon.
+    ///'last_cell_charged' is true right after a sunray charged the last cell
+    pub(crate) fn saturation_imminent(
+        &self,
+        planet_state: &PlanetState,
+        now: i64,
+        last_cell_charged: bool,
+    ) -> bool {
+        let Some(last_sunray) = self.last_time_sunray() else {
+            return false;
+        };
+        if !self.is_saturated(planet_state) || self.estimate_sunray_ms() <= 0.0 {
+            return false;
+        }
+        if let Some(until) = self.until_next_sunray_ms(now) {
+            // The sunray of a fixed timer is expected at a known time
+            let lead_ms = self.config().periodicity.unwrap_or_default().sunray_lead_ms;
+            return until <= lead_ms;
+        }
+        // The sunray filling the cells may be the last event before the next sunray, so it is the last chance
+        last_cell_charged
+            || (now - last_sunray) as f64
+                > SATURATION_IMMINENT_THRESHOLD * self.estimate_sunray_ms()
+    }
+
+    ///Function for freeing a charged cell before a sunray hits full cells:
+    ///the charge becomes a rocket if missing, otherwise carbon for the stock
+    pub(crate) fn free_cell_for_sunray(
+        &mut self,
+        planet_state: &mut PlanetState,
+        generator: &Generator,
+    ) -> Result<(), String> {
+        let message = if !planet_state.has_rocket()
+            && planet_state.can_have_rocket()
+            && self.wants_rocket(planet_state, RocketTrigger::AvoidSunrayWaste)
+        {
+            self.build_rocket(planet_state)?;
+            "Built a rocket to avoid wasting the next sunray"
+        } else {
+            if self.stockpile().is_full() {
+                return Err("The stockpile is full, the sunray will be wasted".to_string());
+            }
+            // The stocked carbon goes to the explorers, so a safe cell is never converted
+            let charged_cells = planet_state.cells_iter().filter(|c| c.is_charged()).count() as u32;
+            if charged_cells <= self.current_safe_cells(planet_state) {
+                return Err("Every charged cell is safe, the sunray will be wasted".to_string());
+            }
+            let carbon = match planet_state.full_cell() {
+                Some((cell, _)) => generator.make_carbon(cell)?,
+                None => return Err("Didn't find any charged cell to free".to_string()),
+            };
+            // Can't fail, the stockpile is not full
+            let _ = self.stock_carbon(carbon);
+            "Converted a charged cell into carbon to avoid wasting the next sunray"
+        };
+
+        self.metrics_handle()
+            .record(|m| m.sunrays_waste_avoided += 1);
+        self.log(
+            LogCategory::Action,
+            Some(Participant::new(ActorType::User, planet_state.id())),
+            None,
+            EventType::InternalPlanetAction,
+            Channel::Info,
+            [("message", message)],
+        );
+        Ok(())
+    }
+
+    ///Function that frees a cell if a sunray is predicted to arrive on full cells
+    pub(crate) fn prevent_sunray_waste(
+        &mut self,
+        planet_state: &mut PlanetState,
+        generator: &Generator,
+        last_cell_charged: bool,
+    ) {
+        if self.avoid_sunray_waste()
+            && self.saturation_imminent(planet_state, self.now(), last_cell_charged)
+            && let Err(e) = self.free_cell_for_sunray(planet_state, generator)
+        {
+            self.log(
+                LogCategory::Action,
+                Some(Participant::new(ActorType::User, planet_state.id())),
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Info, // Expected, e.g. with a full stockpile
+                [("message", e)],
+            );
+        }
+    }
+}
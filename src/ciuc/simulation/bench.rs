@@ -0,0 +1,231 @@
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::events::SimEventKind;
+use crate::ciuc::simulation::simulator::{RandomStream, SimStats, Simulation};
+use std::fmt::Write;
+
+/// Explorer sending the carbon requests in the benchmark
+const BENCH_EXPLORER_ID: u32 = 1;
+
+/// Configuration of the AI under test, with the name shown in the report
+#[derive(Debug, Clone)]
+pub struct BenchPolicy {
+    pub name: String,
+    pub config: CiucConfig,
+}
+
+impl BenchPolicy {
+    ///Function that creates a named policy
+    pub fn new(name: impl Into<String>, config: CiucConfig) -> Self {
+        BenchPolicy {
+            name: name.into(),
+            config,
+        }
+    }
+
+    ///Function that returns the default AI ("statistic") and the same AI never leaving 'SafeState' ("safe")
+    pub fn statistic_vs_safe() -> Vec<BenchPolicy> {
+        vec![
+            BenchPolicy::new("statistic", CiucConfig::default()),
+            BenchPolicy::new(
+                "safe",
+                CiucConfig {
+                    statistic_state: false,
+                    ..Default::default()
+                },
+            ),
+        ]
+    }
+}
+
+/// Environments in which every policy is simulated: each combination of the intervals, with 'seeds' runs each
+#[derive(Debug, Clone)]
+pub struct BenchGrid {
+    pub sunray_interval_ms: Vec<u64>,
+    pub asteroid_interval_ms: Vec<u64>,
+    /// Time between two carbon requests of the explorer
+    pub request_interval_ms: Vec<u64>,
+    /// Jitter of every stream, as a fraction of its interval
+    pub jitter: f64,
+    /// Number of seeded runs for each combination, the same seeds are used for every policy
+    pub seeds: u64,
+    pub duration_ms: u64,
+}
+
+impl Default for BenchGrid {
+    fn default() -> Self {
+        BenchGrid {
+            sunray_interval_ms: vec![200, 500, 1_000],
+            asteroid_interval_ms: vec![2_000, 5_000, 10_000],
+            request_interval_ms: vec![250, 1_000],
+            jitter: 0.3,
+            seeds: 10,
+            duration_ms: 60_000,
+        }
+    }
+}
+
+impl BenchGrid {
+    ///Function that returns the simulation of a policy in an environment
+    pub fn simulation(
+        &self,
+        policy: &BenchPolicy,
+        sunray_interval_ms: u64,
+        asteroid_interval_ms: u64,
+        request_interval_ms: u64,
+        seed: u64,
+    ) -> Simulation {
+        let stream = |kind, interval_ms: u64| RandomStream {
+            kind,
+            interval_ms,
+            jitter_ms: (interval_ms as f64 * self.jitter) as u64,
+        };
+        Simulation::new(policy.config.clone(), self.duration_ms)
+            .with_seed(seed)
+            .stream(stream(SimEventKind::Sunray, sunray_interval_ms))
+            .stream(stream(SimEventKind::Asteroid, asteroid_interval_ms))
+            .stream(stream(
+                SimEventKind::CarbonRequest {
+                    explorer_id: BENCH_EXPLORER_ID,
+                },
+                request_interval_ms,
+            ))
+    }
+}
+
+/// Results of a policy in an environment, averaged over the seeded runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchRow {
+    pub policy: String,
+    pub sunray_interval_ms: u64,
+    pub asteroid_interval_ms: u64,
+    pub request_interval_ms: u64,
+    pub runs: u64,
+    /// Fraction of the runs in which the planet survived
+    pub survival_rate: f64,
+    /// Mean carbon delivered per run
+    pub carbon_delivered: f64,
+    /// Mean carbon requests per run
+    pub carbon_requests: f64,
+    /// Mean sunrays wasted on full cells per run
+    pub sunrays_wasted: f64,
+}
+
+impl BenchRow {
+    fn from_runs(
+        policy: &BenchPolicy,
+        sunray_interval_ms: u64,
+        asteroid_interval_ms: u64,
+        request_interval_ms: u64,
+        runs: &[SimStats],
+    ) -> Self {
+        let n = runs.len().max(1) as f64;
+        let mean = |f: fn(&SimStats) -> u64| runs.iter().map(f).sum::<u64>() as f64 / n;
+        BenchRow {
+            policy: policy.name.clone(),
+            sunray_interval_ms,
+            asteroid_interval_ms,
+            request_interval_ms,
+            runs: runs.len() as u64,
+            survival_rate: runs.iter().filter(|s| s.survived).count() as f64 / n,
+            carbon_delivered: mean(|s| s.carbon_delivered),
+            carbon_requests: mean(|s| s.carbon_requests),
+            sunrays_wasted: mean(|s| s.sunrays_wasted),
+        }
+    }
+}
+
+/// Results of a benchmark, one row per policy and environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub rows: Vec<BenchRow>,
+}
+
+const CSV_HEADER: &str = "policy,sunray_interval_ms,asteroid_interval_ms,request_interval_ms,runs,survival_rate,carbon_delivered,carbon_requests,sunrays_wasted";
+
+impl BenchReport {
+    ///Function that returns the rows in CSV format, with a header
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{CSV_HEADER}");
+        for r in &self.rows {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{:.4},{:.2},{:.2},{:.2}",
+                csv_field(&r.policy),
+                r.sunray_interval_ms,
+                r.asteroid_interval_ms,
+                r.request_interval_ms,
+                r.runs,
+                r.survival_rate,
+                r.carbon_delivered,
+                r.carbon_requests,
+                r.sunrays_wasted
+            );
+        }
+        out
+    }
+
+    ///Function that returns the rows as an aligned table, to be printed
+    pub fn to_table(&self) -> String {
+        let width = self
+            .rows
+            .iter()
+            .map(|r| r.policy.len())
+            .max()
+            .unwrap_or(0)
+            .max("policy".len());
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:<width$} {:>9} {:>11} {:>10} {:>9} {:>9} {:>9}",
+            "policy", "sunray_ms", "asteroid_ms", "request_ms", "survival", "carbon", "wasted"
+        );
+        for r in &self.rows {
+            let _ = writeln!(
+                out,
+                "{:<width$} {:>9} {:>11} {:>10} {:>8.1}% {:>9.1} {:>9.1}",
+                r.policy,
+                r.sunray_interval_ms,
+                r.asteroid_interval_ms,
+                r.request_interval_ms,
+                r.survival_rate * 100.0,
+                r.carbon_delivered,
+                r.sunrays_wasted
+            );
+        }
+        out
+    }
+}
+
+///Function that quotes a CSV field if it contains a separator, a quote or a line break (RFC 4180)
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+///Function that simulates every policy in every environment of the grid
+pub fn run_benchmark(policies: &[BenchPolicy], grid: &BenchGrid) -> Result<BenchReport, String> {
+    let mut rows = Vec::new();
+    for &sunray in &grid.sunray_interval_ms {
+        for &asteroid in &grid.asteroid_interval_ms {
+            for &request in &grid.request_interval_ms {
+                for policy in policies {
+                    let runs = (0..grid.seeds)
+                        .map(|seed| {
+                            grid.simulation(policy, sunray, asteroid, request, seed)
+                                .run()
+                                .map(|report| report.stats)
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+                    rows.push(BenchRow::from_runs(
+                        policy, sunray, asteroid, request, &runs,
+                    ));
+                }
+            }
+        }
+    }
+    Ok(BenchReport { rows })
+}
@@ -0,0 +1,17 @@
+mod bench;
+mod replay;
+mod rng;
+mod scenario;
+mod simulator;
+mod training;
+mod tuner;
+
+pub use bench::{BenchGrid, BenchPolicy, BenchReport, BenchRow, run_benchmark};
+pub use replay::{Divergence, ReplayReport, replay, replay_file};
+pub use rng::SimRng;
+pub use crate::ciuc::events::{SimEventKind, SimOutcome};
+pub use simulator::{
+    RandomStream, SIM_EPOCH_MS, SimEvent, SimReport, SimStats, Simulation, TimelineEntry,
+};
+pub use training::train_q_learning;
+pub use tuner::{Evaluation, Objective, SearchMethod, SearchSpace, TuneResult, Tuner};
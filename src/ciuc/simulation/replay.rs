@@ -0,0 +1,78 @@
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::events::{SimEventKind, SimOutcome};
+use crate::ciuc::simulation::simulator::{SimEvent, SimReport, Simulation};
+use crate::ciuc::trace::{TraceRecord, read_trace};
+use std::path::Path;
+
+/// Event for which the replayed AI didn't do what the recorded AI did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position of the record in the trace
+    pub index: usize,
+    /// Time of the AI clock in the recorded run
+    pub at_ms: i64,
+    pub event: SimEventKind,
+    pub recorded: SimOutcome,
+    /// `None` if the replayed planet was destroyed before this event
+    pub replayed: Option<SimOutcome>,
+}
+
+/// Result of a replay
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    pub divergences: Vec<Divergence>,
+    pub simulation: SimReport,
+}
+
+impl ReplayReport {
+    ///Function that returns the first event where the replay diverged
+    pub fn first_divergence(&self) -> Option<&Divergence> {
+        self.divergences.first()
+    }
+}
+
+///Function that feeds a recorded trace into a fresh AI with the given configuration,
+///keeping the recorded time between the events
+pub fn replay(records: &[TraceRecord], config: CiucConfig) -> Result<ReplayReport, String> {
+    let mut config = config;
+    config.trace_path = None; // The replay must not overwrite the trace it reads
+
+    let start = records.first().map_or(0, |r| r.at_ms);
+    let mut at_ms = 0;
+    let mut simulation = Simulation::new(config, 0);
+    for record in records {
+        // A clock going backwards would reorder the events
+        at_ms = at_ms.max(record.at_ms.saturating_sub(start).max(0) as u64);
+        simulation.events.push(SimEvent {
+            at_ms,
+            kind: record.event,
+        });
+    }
+    simulation.duration_ms = at_ms;
+
+    let report = simulation.run()?;
+    let divergences = records
+        .iter()
+        .enumerate()
+        .filter_map(|(index, record)| {
+            let replayed = report.timeline.get(index).map(|e| e.outcome);
+            (replayed != Some(record.outcome)).then_some(Divergence {
+                index,
+                at_ms: record.at_ms,
+                event: record.event,
+                recorded: record.outcome,
+                replayed,
+            })
+        })
+        .collect();
+
+    Ok(ReplayReport {
+        divergences,
+        simulation: report,
+    })
+}
+
+///Function that replays the trace saved in a file
+pub fn replay_file(path: impl AsRef<Path>, config: CiucConfig) -> Result<ReplayReport, String> {
+    replay(&read_trace(path)?, config)
+}
@@ -0,0 +1,195 @@
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::events::SimEventKind;
+use crate::ciuc::simulation::simulator::{RandomStream, SimEvent, Simulation};
+use std::fs;
+use std::path::Path;
+
+// Scenario format, one directive per line ('#' starts a comment):
+//
+//   duration 60s
+//   seed 42
+//   sunray at 100ms
+//   asteroid every 2s ±500ms
+//   explorer 7 arrives at 1s
+//   explorer 7 requests carbon x3 at 1.5s
+//   explorer 7 requests cells every 800ms +-100ms
+//   explorer 7 leaves at 50s
+//
+// Times are in 'ms' or 's', a bare number is in milliseconds. An event without a time happens at 0ms.
+// 'xN' repeats an 'at' event N times. 'duration' is required with 'every', otherwise it defaults to the last event.
+// An event after the duration, or an 'every' whose first event can't fall within it, is an error.
+
+impl Simulation {
+    ///Function that creates a simulation from the text of a scenario
+    pub fn from_scenario(text: &str, config: CiucConfig) -> Result<Self, String> {
+        let mut simulation = Simulation::new(config, 0);
+        let mut duration_ms = None;
+        let mut event_lines = Vec::new(); // Line of each event and stream, for the errors
+        let mut stream_lines = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            parse_line(&words, &mut simulation, &mut duration_ms)
+                .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+            event_lines.resize(simulation.events.len(), i + 1);
+            stream_lines.resize(simulation.streams.len(), i + 1);
+        }
+
+        if let Some(duration) = duration_ms {
+            for (event, line) in simulation.events.iter().zip(&event_lines) {
+                if event.at_ms > duration {
+                    return Err(format!(
+                        "Line {}: the event at {}ms is after the duration of {}ms",
+                        line, event.at_ms, duration
+                    ));
+                }
+            }
+            for (stream, line) in simulation.streams.iter().zip(&stream_lines) {
+                if stream.interval_ms.saturating_sub(stream.jitter_ms) > duration {
+                    return Err(format!(
+                        "Line {}: an event every {}ms never happens within the duration of {}ms",
+                        line, stream.interval_ms, duration
+                    ));
+                }
+            }
+        }
+
+        simulation.duration_ms = match duration_ms {
+            Some(d) => d,
+            None if simulation.streams.is_empty() => {
+                simulation.events.iter().map(|e| e.at_ms).max().unwrap_or(0)
+            }
+            None => return Err("A scenario with 'every' events needs a 'duration'".to_string()),
+        };
+        Ok(simulation)
+    }
+
+    ///Function that creates a simulation from a scenario file
+    pub fn load_scenario(path: impl AsRef<Path>, config: CiucConfig) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Can't read the scenario {}: {}", path.display(), e))?;
+        Simulation::from_scenario(&text, config)
+            .map_err(|e| format!("Invalid scenario {}: {}", path.display(), e))
+    }
+}
+
+fn parse_line(
+    words: &[&str],
+    simulation: &mut Simulation,
+    duration_ms: &mut Option<u64>,
+) -> Result<(), String> {
+    match words {
+        ["duration", time] => {
+            *duration_ms = Some(parse_time(time)?);
+            return Ok(());
+        }
+        ["seed", seed] => {
+            simulation.seed = seed
+                .parse()
+                .map_err(|_| format!("Invalid seed '{}'", seed))?;
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let (kind, rest) = parse_event(words)?;
+    let (times, rest) = match rest {
+        [repeat, rest @ ..] if repeat.starts_with('x') => {
+            let times = repeat
+                .trim_start_matches('x')
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid repetition '{}'", repeat))?;
+            (times, rest)
+        }
+        rest => (1, rest),
+    };
+
+    match rest {
+        [] => push_times(simulation, 0, kind, times),
+        ["at", time] => push_times(simulation, parse_time(time)?, kind, times),
+        ["every", interval, jitter @ ..] => {
+            if times != 1 {
+                return Err("'xN' can't be used with 'every'".to_string());
+            }
+            let jitter_ms = match jitter {
+                [] => 0,
+                [jitter] => parse_jitter(jitter)?,
+                ["±" | "+-", time] => parse_time(time)?,
+                _ => return Err(format!("Unexpected '{}'", jitter.join(" "))),
+            };
+            simulation.streams.push(RandomStream {
+                kind,
+                interval_ms: parse_time(interval)?,
+                jitter_ms,
+            });
+        }
+        _ => {
+            return Err(format!(
+                "Expected 'at <time>' or 'every <time>', found '{}'",
+                rest.join(" ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn push_times(simulation: &mut Simulation, at_ms: u64, kind: SimEventKind, times: u32) {
+    for _ in 0..times {
+        simulation.events.push(SimEvent { at_ms, kind });
+    }
+}
+
+///Function that reads the event at the start of the line, returning the words after it
+fn parse_event<'a, 'b>(words: &'a [&'b str]) -> Result<(SimEventKind, &'a [&'b str]), String> {
+    match words {
+        ["sunray", rest @ ..] => Ok((SimEventKind::Sunray, rest)),
+        ["asteroid", rest @ ..] => Ok((SimEventKind::Asteroid, rest)),
+        ["explorer", id, action @ ..] => {
+            let explorer_id = id
+                .parse()
+                .map_err(|_| format!("Invalid explorer id '{}'", id))?;
+            match action {
+                ["arrives", rest @ ..] => Ok((SimEventKind::ExplorerArrival { explorer_id }, rest)),
+                ["leaves", rest @ ..] => {
+                    Ok((SimEventKind::ExplorerDeparture { explorer_id }, rest))
+                }
+                ["requests", "carbon", rest @ ..] => {
+                    Ok((SimEventKind::CarbonRequest { explorer_id }, rest))
+                }
+                ["requests", "cells", rest @ ..] => {
+                    Ok((SimEventKind::AvailableCellsRequest { explorer_id }, rest))
+                }
+                _ => Err(format!("Unknown explorer action '{}'", action.join(" "))),
+            }
+        }
+        _ => Err(format!("Unknown event '{}'", words.join(" "))),
+    }
+}
+
+///Function that reads a time like '100ms', '2s', '1.5s' or '100' (milliseconds)
+fn parse_time(word: &str) -> Result<u64, String> {
+    let (number, scale) = if let Some(n) = word.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = word.strip_suffix('s') {
+        (n, 1000.0)
+    } else {
+        (word, 1.0)
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok((n * scale).round() as u64),
+        _ => Err(format!("Invalid time '{}'", word)),
+    }
+}
+
+///Function that reads a jitter like '±500ms' or '+-500ms'
+fn parse_jitter(word: &str) -> Result<u64, String> {
+    match word.strip_prefix('±').or_else(|| word.strip_prefix("+-")) {
+        Some(time) => parse_time(time),
+        None => Err(format!("Invalid jitter '{}', expected '±<time>'", word)),
+    }
+}
@@ -0,0 +1,447 @@
+use crate::CiucAI;
+use crate::ciuc::clock::Clock;
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::create_planet::create_planet_with_ai;
+use crate::ciuc::decision::CarbonDecision;
+use crate::ciuc::events::{SimEventKind, SimOutcome};
+use crate::ciuc::metrics::{CiucMetrics, MetricsHandle};
+use crate::ciuc::q_learning::QTable;
+use crate::ciuc::simulation::rng::SimRng;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::DummyPlanetState;
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use crossbeam_channel::{Receiver, Sender};
+use std::collections::HashMap;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Virtual time at which every simulation starts. It is far from zero like the system clock,
+/// so the AI sees the same "time since the last event" as in a real run before the first event.
+pub const SIM_EPOCH_MS: i64 = 1_700_000_000_000;
+
+/// How long to wait for the planet to answer a message before giving up
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Event scheduled at 'at_ms' milliseconds from the start of the simulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimEvent {
+    pub at_ms: u64,
+    pub kind: SimEventKind,
+}
+
+/// Event repeated every 'interval_ms', each interval moved by a random amount in [-jitter_ms, +jitter_ms]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RandomStream {
+    pub kind: SimEventKind,
+    pub interval_ms: u64,
+    pub jitter_ms: u64,
+}
+
+impl RandomStream {
+    ///Function that returns the events of the stream up to 'duration_ms'
+    pub fn generate(&self, duration_ms: u64, rng: &mut SimRng) -> Vec<SimEvent> {
+        let mut events = Vec::new();
+        if self.interval_ms == 0 {
+            return events;
+        }
+        let jitter = self.jitter_ms.min(self.interval_ms) as i64;
+        let mut at_ms = 0u64;
+        loop {
+            let interval = self.interval_ms as i64 + rng.range_i64(-jitter, jitter);
+            at_ms += interval.max(1) as u64;
+            if at_ms > duration_ms {
+                return events;
+            }
+            events.push(SimEvent {
+                at_ms,
+                kind: self.kind,
+            });
+        }
+    }
+}
+
+/// Event of the timeline, with the state of the planet after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub at_ms: u64,
+    pub kind: SimEventKind,
+    pub outcome: SimOutcome,
+    pub charged_cells: usize,
+    /// Energy cells of the planet
+    pub cell_count: usize,
+    pub has_rocket: bool,
+    pub stocked_carbon: u64,
+    /// Safe cells of the last decision or answer of the AI
+    pub safe_cells: u32,
+}
+
+/// Summary of a simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimStats {
+    pub survived: bool,
+    pub destroyed_at_ms: Option<u64>,
+    pub simulated_ms: u64,
+    pub sunrays: u64,
+    pub sunrays_wasted: u64,
+    pub asteroids: u64,
+    pub asteroids_deflected: u64,
+    pub carbon_requests: u64,
+    pub carbon_delivered: u64,
+    /// Real time spent running the simulation
+    pub wall_time_ms: f64,
+}
+
+/// Everything produced by a simulation
+#[derive(Debug, Clone)]
+pub struct SimReport {
+    pub timeline: Vec<TimelineEntry>,
+    pub stats: SimStats,
+    pub metrics: CiucMetrics,
+    pub decisions: Vec<CarbonDecision>,
+    /// Table learned by the Q-learning policy, if configured
+    pub q_table: Option<QTable>,
+}
+
+/// Deterministic simulation of a planet driven by the Ciuc AI.
+/// The planet runs on its own thread as in the game, but each event waits for the planet's answer
+/// and the AI reads a virtual clock, so no time is spent sleeping and every run gives the same result.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub config: CiucConfig,
+    pub planet_id: u32,
+    pub duration_ms: u64,
+    pub seed: u64,
+    /// Scripted events
+    pub events: Vec<SimEvent>,
+    /// Randomized events, generated from 'seed'
+    pub streams: Vec<RandomStream>,
+}
+
+impl Simulation {
+    ///Function that creates an empty simulation lasting 'duration_ms'
+    pub fn new(config: CiucConfig, duration_ms: u64) -> Self {
+        Simulation {
+            config,
+            planet_id: 0,
+            duration_ms,
+            seed: 0,
+            events: Vec::new(),
+            streams: Vec::new(),
+        }
+    }
+
+    ///Function for setting the seed of the randomized streams
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    ///Function for adding a scripted event
+    pub fn event(mut self, at_ms: u64, kind: SimEventKind) -> Self {
+        self.events.push(SimEvent { at_ms, kind });
+        self
+    }
+
+    ///Function for adding a randomized stream of events
+    pub fn stream(mut self, stream: RandomStream) -> Self {
+        self.streams.push(stream);
+        self
+    }
+
+    ///Function that returns every event of the simulation in time order.
+    ///Events at the same time keep the order in which they were added, scripted ones first
+    pub fn schedule(&self) -> Vec<SimEvent> {
+        let mut rng = SimRng::new(self.seed);
+        let mut events: Vec<SimEvent> = self
+            .events
+            .iter()
+            .copied()
+            .filter(|e| e.at_ms <= self.duration_ms)
+            .collect();
+        for stream in &self.streams {
+            events.extend(stream.generate(self.duration_ms, &mut rng));
+        }
+        events.sort_by_key(|e| e.at_ms);
+        events
+    }
+
+    ///Function that runs the simulation, it stops early if the planet is destroyed
+    pub fn run(&self) -> Result<SimReport, String> {
+        let started = Instant::now();
+        let clock = Clock::manual(SIM_EPOCH_MS);
+        let ai = CiucAI::with_clock(self.config.clone(), clock.clone());
+        let decisions = ai.decision_log();
+        let metrics = ai.metrics();
+        let q_table = ai.q_table();
+        let mut planet = SimPlanet::start(self.planet_id, ai, metrics.clone())?;
+
+        let mut timeline = Vec::new();
+        let mut stats = SimStats {
+            survived: true,
+            destroyed_at_ms: None,
+            simulated_ms: self.duration_ms,
+            sunrays: 0,
+            sunrays_wasted: 0,
+            asteroids: 0,
+            asteroids_deflected: 0,
+            carbon_requests: 0,
+            carbon_delivered: 0,
+            wall_time_ms: 0.0,
+        };
+
+        for event in self.schedule() {
+            clock.set(SIM_EPOCH_MS + event.at_ms as i64);
+            let outcome = planet.apply(event.kind)?;
+            match outcome {
+                SimOutcome::SunrayCharged => stats.sunrays += 1,
+                SimOutcome::SunrayWasted => {
+                    stats.sunrays += 1;
+                    stats.sunrays_wasted += 1;
+                }
+                SimOutcome::AsteroidDeflected => {
+                    stats.asteroids += 1;
+                    stats.asteroids_deflected += 1;
+                }
+                SimOutcome::PlanetDestroyed => {
+                    stats.asteroids += 1;
+                    stats.survived = false;
+                    stats.destroyed_at_ms = Some(event.at_ms);
+                    stats.simulated_ms = event.at_ms;
+                }
+                SimOutcome::CarbonGranted => {
+                    stats.carbon_requests += 1;
+                    stats.carbon_delivered += 1;
+                }
+                SimOutcome::CarbonRefused => stats.carbon_requests += 1,
+                _ => {}
+            }
+
+            let dummy = planet.internal_state()?;
+            let snapshot = metrics.snapshot();
+            timeline.push(TimelineEntry {
+                at_ms: event.at_ms,
+                kind: event.kind,
+                outcome,
+                charged_cells: dummy.charged_cells_count,
+                cell_count: dummy.energy_cells.len(),
+                has_rocket: dummy.has_rocket,
+                stocked_carbon: snapshot.carbon_stocked,
+                safe_cells: snapshot.safe_cells,
+            });
+
+            if !stats.survived {
+                break;
+            }
+        }
+
+        planet.kill()?;
+        clock.set(SIM_EPOCH_MS + stats.simulated_ms as i64);
+        stats.wall_time_ms = started.elapsed().as_secs_f64() * 1000.0;
+        Ok(SimReport {
+            timeline,
+            stats,
+            metrics: metrics.snapshot(),
+            decisions: decisions.all(),
+            q_table: q_table.map(|t| t.snapshot()),
+        })
+    }
+}
+
+/// Planet running on its own thread, driven one message at a time
+struct SimPlanet {
+    id: u32,
+    to_planet: Sender<OrchestratorToPlanet>,
+    from_planet: Receiver<PlanetToOrchestrator>,
+    explorer_to_planet: Sender<ExplorerToPlanet>,
+    explorers: HashMap<u32, Receiver<PlanetToExplorer>>,
+    metrics: MetricsHandle,
+    handle: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl SimPlanet {
+    fn start(id: u32, ai: CiucAI, metrics: MetricsHandle) -> Result<Self, String> {
+        let (to_planet, rx_orchestrator) = crossbeam_channel::unbounded();
+        let (tx_orchestrator, from_planet) = crossbeam_channel::unbounded();
+        let (explorer_to_planet, rx_explorer) = crossbeam_channel::unbounded();
+        let mut planet =
+            create_planet_with_ai(rx_orchestrator, tx_orchestrator, rx_explorer, id, ai);
+        let handle = thread::spawn(move || planet.run());
+
+        let mut sim_planet = SimPlanet {
+            id,
+            to_planet,
+            from_planet,
+            explorer_to_planet,
+            explorers: HashMap::new(),
+            metrics,
+            handle: Some(handle),
+        };
+        match sim_planet.orchestrator(OrchestratorToPlanet::StartPlanetAI)? {
+            PlanetToOrchestrator::StartPlanetAIResult { .. } => Ok(sim_planet),
+            other => Err(format!("Unexpected answer to the start: {:?}", other)),
+        }
+    }
+
+    ///Function that sends a message to the planet and waits for its answer
+    fn orchestrator(&mut self, msg: OrchestratorToPlanet) -> Result<PlanetToOrchestrator, String> {
+        self.to_planet
+            .send(msg)
+            .map_err(|_| format!("Planet {} disconnected", self.id))?;
+        self.from_planet
+            .recv_timeout(RESPONSE_TIMEOUT)
+            .map_err(|e| format!("Planet {} didn't answer: {}", self.id, e))
+    }
+
+    ///Function that sends a message as an explorer and waits for the answer
+    fn explorer(
+        &mut self,
+        explorer_id: u32,
+        msg: ExplorerToPlanet,
+    ) -> Result<PlanetToExplorer, String> {
+        if !self.explorers.contains_key(&explorer_id) {
+            self.arrive(explorer_id)?;
+        }
+        self.explorer_to_planet
+            .send(msg)
+            .map_err(|_| format!("Planet {} disconnected", self.id))?;
+        self.explorers[&explorer_id]
+            .recv_timeout(RESPONSE_TIMEOUT)
+            .map_err(|e| {
+                format!(
+                    "Planet {} didn't answer explorer {}: {}",
+                    self.id, explorer_id, e
+                )
+            })
+    }
+
+    fn arrive(&mut self, explorer_id: u32) -> Result<(), String> {
+        let (new_sender, receiver) = crossbeam_channel::unbounded();
+        self.explorers.insert(explorer_id, receiver);
+        match self.orchestrator(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender,
+        })? {
+            PlanetToOrchestrator::IncomingExplorerResponse { res: Ok(()), .. } => Ok(()),
+            other => Err(format!(
+                "Unexpected answer to the explorer arrival: {:?}",
+                other
+            )),
+        }
+    }
+
+    fn apply(&mut self, kind: SimEventKind) -> Result<SimOutcome, String> {
+        match kind {
+            SimEventKind::Sunray => {
+                let wasted_before = self.metrics.snapshot().sunrays_wasted;
+                match self.orchestrator(OrchestratorToPlanet::Sunray(Sunray::default()))? {
+                    PlanetToOrchestrator::SunrayAck { .. } => {
+                        if self.metrics.snapshot().sunrays_wasted > wasted_before {
+                            Ok(SimOutcome::SunrayWasted)
+                        } else {
+                            Ok(SimOutcome::SunrayCharged)
+                        }
+                    }
+                    other => Err(format!("Unexpected answer to a sunray: {:?}", other)),
+                }
+            }
+            SimEventKind::Asteroid => {
+                match self.orchestrator(OrchestratorToPlanet::Asteroid(Asteroid::default()))? {
+                    PlanetToOrchestrator::AsteroidAck {
+                        rocket: Some(_), ..
+                    } => Ok(SimOutcome::AsteroidDeflected),
+                    PlanetToOrchestrator::AsteroidAck { rocket: None, .. } => {
+                        Ok(SimOutcome::PlanetDestroyed)
+                    }
+                    other => Err(format!("Unexpected answer to an asteroid: {:?}", other)),
+                }
+            }
+            SimEventKind::ExplorerArrival { explorer_id } => {
+                self.arrive(explorer_id)?;
+                Ok(SimOutcome::ExplorerArrived)
+            }
+            SimEventKind::ExplorerDeparture { explorer_id } => {
+                self.explorers.remove(&explorer_id);
+                match self
+                    .orchestrator(OrchestratorToPlanet::OutgoingExplorerRequest { explorer_id })?
+                {
+                    PlanetToOrchestrator::OutgoingExplorerResponse { res: Ok(()), .. } => {
+                        Ok(SimOutcome::ExplorerDeparted)
+                    }
+                    other => Err(format!(
+                        "Unexpected answer to the explorer departure: {:?}",
+                        other
+                    )),
+                }
+            }
+            SimEventKind::CarbonRequest { explorer_id } => {
+                let msg = ExplorerToPlanet::GenerateResourceRequest {
+                    explorer_id,
+                    resource: BasicResourceType::Carbon,
+                };
+                match self.explorer(explorer_id, msg)? {
+                    PlanetToExplorer::GenerateResourceResponse { resource: Some(_) } => {
+                        Ok(SimOutcome::CarbonGranted)
+                    }
+                    PlanetToExplorer::GenerateResourceResponse { resource: None } => {
+                        Ok(SimOutcome::CarbonRefused)
+                    }
+                    other => Err(format!(
+                        "Unexpected answer to a carbon request: {:?}",
+                        other
+                    )),
+                }
+            }
+            SimEventKind::AvailableCellsRequest { explorer_id } => {
+                let msg = ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id };
+                match self.explorer(explorer_id, msg)? {
+                    PlanetToExplorer::AvailableEnergyCellResponse { available_cells } => {
+                        Ok(SimOutcome::AvailableCells(available_cells))
+                    }
+                    other => Err(format!(
+                        "Unexpected answer to an available cells request: {:?}",
+                        other
+                    )),
+                }
+            }
+            SimEventKind::OtherExplorerRequest { explorer_id } => {
+                let msg = ExplorerToPlanet::SupportedResourceRequest { explorer_id };
+                self.explorer(explorer_id, msg)?;
+                Ok(SimOutcome::Answered)
+            }
+        }
+    }
+
+    fn internal_state(&mut self) -> Result<DummyPlanetState, String> {
+        match self.orchestrator(OrchestratorToPlanet::InternalStateRequest)? {
+            PlanetToOrchestrator::InternalStateResponse { planet_state, .. } => Ok(planet_state),
+            other => Err(format!(
+                "Unexpected answer to the internal state request: {:?}",
+                other
+            )),
+        }
+    }
+
+    fn kill(&mut self) -> Result<(), String> {
+        match self.orchestrator(OrchestratorToPlanet::KillPlanet)? {
+            PlanetToOrchestrator::KillPlanetResult { .. } => {}
+            other => return Err(format!("Unexpected answer to the kill: {:?}", other)),
+        }
+        match self.handle.take().map(|h| h.join()) {
+            Some(Ok(res)) => res,
+            Some(Err(_)) => Err(format!("The thread of planet {} panicked", self.id)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for SimPlanet {
+    fn drop(&mut self) {
+        // If the simulation failed midway, the planet thread is still waiting for messages
+        if self.handle.is_some() {
+            let _ = self.to_planet.send(OrchestratorToPlanet::KillPlanet);
+        }
+    }
+}
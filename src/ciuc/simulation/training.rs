@@ -0,0 +1,47 @@
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::q_learning::{QLearningConfig, QTable};
+use crate::ciuc::simulation::bench::{BenchGrid, BenchPolicy};
+
+///Function that trains the Q-learning policy for 'episodes' simulations, cycling through the environments
+///of the grid with a new seed each time, and returns the learned table (to be put in `QLearningConfig::table`)
+pub fn train_q_learning(
+    base: &CiucConfig,
+    q_learning: &QLearningConfig,
+    environments: &BenchGrid,
+    episodes: u64,
+) -> Result<QTable, String> {
+    let mut combinations = Vec::new();
+    for &sunray in &environments.sunray_interval_ms {
+        for &asteroid in &environments.asteroid_interval_ms {
+            for &request in &environments.request_interval_ms {
+                combinations.push((sunray, asteroid, request));
+            }
+        }
+    }
+    if combinations.is_empty() {
+        return Err("The grid has no environment to train in".to_string());
+    }
+
+    let mut table = q_learning.table.clone();
+    for episode in 0..episodes {
+        let (sunray, asteroid, request) = combinations[episode as usize % combinations.len()];
+        let policy = BenchPolicy::new(
+            "training",
+            CiucConfig {
+                q_learning: Some(QLearningConfig {
+                    table,
+                    seed: q_learning.seed.wrapping_add(episode),
+                    ..q_learning.clone()
+                }),
+                ..base.clone()
+            },
+        );
+        let report = environments
+            .simulation(&policy, sunray, asteroid, request, episode)
+            .run()?;
+        table = report
+            .q_table
+            .ok_or("The simulation didn't return the Q-table")?;
+    }
+    Ok(table)
+}
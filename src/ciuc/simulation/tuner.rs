@@ -0,0 +1,334 @@
+use crate::ciuc::carbon::SafeCellsConfig;
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::simulation::bench::{BenchGrid, BenchPolicy, BenchRow, run_benchmark};
+use crate::ciuc::simulation::rng::SimRng;
+use std::ops::RangeInclusive;
+
+/// What the tuner maximizes: 'carbon_weight' * carbon delivered - 'waste_weight' * sunrays wasted,
+/// among the configurations surviving at least 'min_survival_rate' of the runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Objective {
+    pub min_survival_rate: f64,
+    pub carbon_weight: f64,
+    pub waste_weight: f64,
+}
+
+/// Score below which the configurations don't reach the survival rate, far below any carbon count
+const INFEASIBLE_PENALTY: f64 = 1e6;
+
+impl Objective {
+    ///Function that returns the value of the objective for the mean results of a configuration
+    pub fn score(&self, survival_rate: f64, carbon_delivered: f64, sunrays_wasted: f64) -> f64 {
+        if survival_rate >= self.min_survival_rate {
+            self.carbon_weight * carbon_delivered - self.waste_weight * sunrays_wasted
+        } else {
+            // Infeasible: below the penalty, ranked by how close the survival rate is to the target
+            -INFEASIBLE_PENALTY * (1.0 + self.min_survival_rate - survival_rate)
+        }
+    }
+}
+
+impl Default for Objective {
+    fn default() -> Self {
+        Objective {
+            min_survival_rate: 0.99,
+            carbon_weight: 1.0,
+            waste_weight: 0.0,
+        }
+    }
+}
+
+/// Values tried for each threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSpace {
+    pub safe_cells: RangeInclusive<u32>,
+    pub safe_cells_far_asteroid: RangeInclusive<u32>,
+    pub safe_cells_near_asteroid: RangeInclusive<u32>,
+    pub sunray_imminent_threshold: RangeInclusive<f64>,
+    pub asteroid_far_threshold: RangeInclusive<f64>,
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        SearchSpace {
+            safe_cells: 1..=4,
+            safe_cells_far_asteroid: 0..=3,
+            safe_cells_near_asteroid: 1..=4,
+            sunray_imminent_threshold: 0.3..=1.0,
+            asteroid_far_threshold: 0.2..=0.9,
+        }
+    }
+}
+
+impl SearchSpace {
+    ///Function that returns a random point of the space
+    fn sample(&self, rng: &mut SimRng) -> SafeCellsConfig {
+        let int = |r: &RangeInclusive<u32>, rng: &mut SimRng| {
+            rng.range_i64(*r.start() as i64, *r.end() as i64) as u32
+        };
+        let float = |r: &RangeInclusive<f64>, rng: &mut SimRng| {
+            r.start() + rng.next_f64() * (r.end() - r.start())
+        };
+        self.fix(SafeCellsConfig {
+            safe_cells: int(&self.safe_cells, rng),
+            safe_cells_far_asteroid: int(&self.safe_cells_far_asteroid, rng),
+            safe_cells_near_asteroid: int(&self.safe_cells_near_asteroid, rng),
+            sunray_imminent_threshold: float(&self.sunray_imminent_threshold, rng),
+            asteroid_far_threshold: float(&self.asteroid_far_threshold, rng),
+            ..Default::default()
+        })
+    }
+
+    ///Function that returns the points of a grid with 'steps' values for each fraction
+    fn grid(&self, steps: u32) -> Vec<SafeCellsConfig> {
+        let floats = |r: &RangeInclusive<f64>| -> Vec<f64> {
+            if steps <= 1 {
+                return vec![(r.start() + r.end()) / 2.0];
+            }
+            (0..steps)
+                .map(|i| r.start() + (r.end() - r.start()) * i as f64 / (steps - 1) as f64)
+                .collect()
+        };
+        let mut points = Vec::new();
+        for safe_cells in self.safe_cells.clone() {
+            for far in self.safe_cells_far_asteroid.clone() {
+                for near in self.safe_cells_near_asteroid.clone() {
+                    for &sunray in &floats(&self.sunray_imminent_threshold) {
+                        for &asteroid in &floats(&self.asteroid_far_threshold) {
+                            let point = self.fix(SafeCellsConfig {
+                                safe_cells,
+                                safe_cells_far_asteroid: far,
+                                safe_cells_near_asteroid: near,
+                                sunray_imminent_threshold: sunray,
+                                asteroid_far_threshold: asteroid,
+                                ..Default::default()
+                            });
+                            if !points.contains(&point) {
+                                points.push(point);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    ///Function that moves a point by a random step, staying in the space
+    fn mutate(&self, point: &SafeCellsConfig, rng: &mut SimRng) -> SafeCellsConfig {
+        let int = |v: u32, r: &RangeInclusive<u32>, rng: &mut SimRng| {
+            (v as i64 + rng.range_i64(-1, 1)).clamp(*r.start() as i64, *r.end() as i64) as u32
+        };
+        let float = |v: f64, r: &RangeInclusive<f64>, rng: &mut SimRng| {
+            let step = (r.end() - r.start()) * 0.2 * (rng.next_f64() * 2.0 - 1.0);
+            (v + step).clamp(*r.start(), *r.end())
+        };
+        self.fix(SafeCellsConfig {
+            safe_cells: int(point.safe_cells, &self.safe_cells, rng),
+            safe_cells_far_asteroid: int(
+                point.safe_cells_far_asteroid,
+                &self.safe_cells_far_asteroid,
+                rng,
+            ),
+            safe_cells_near_asteroid: int(
+                point.safe_cells_near_asteroid,
+                &self.safe_cells_near_asteroid,
+                rng,
+            ),
+            sunray_imminent_threshold: float(
+                point.sunray_imminent_threshold,
+                &self.sunray_imminent_threshold,
+                rng,
+            ),
+            asteroid_far_threshold: float(
+                point.asteroid_far_threshold,
+                &self.asteroid_far_threshold,
+                rng,
+            ),
+            ..*point
+        })
+    }
+
+    ///Function that keeps a near asteroid at least as dangerous as a far one
+    fn fix(&self, mut point: SafeCellsConfig) -> SafeCellsConfig {
+        point.safe_cells_near_asteroid = point
+            .safe_cells_near_asteroid
+            .max(point.safe_cells_far_asteroid);
+        point
+    }
+}
+
+/// How the tuner explores the search space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMethod {
+    /// Every combination, with 'steps' values for each fraction
+    Grid { steps: u32 },
+    /// 'samples' random points
+    Random { samples: usize },
+    /// Keeps the best half of the population and mutates it, for 'generations' times
+    Evolutionary {
+        population: usize,
+        generations: usize,
+    },
+}
+
+/// Results of a configuration over all the environments of the grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+    pub survival_rate: f64,
+    /// Mean per run
+    pub carbon_delivered: f64,
+    /// Mean per run
+    pub sunrays_wasted: f64,
+    /// Value of the objective, configurations below the survival rate score below every other one
+    pub score: f64,
+}
+
+/// Recommended configuration
+#[derive(Debug, Clone)]
+pub struct TuneResult {
+    pub config: CiucConfig,
+    pub evaluation: Evaluation,
+    /// Evaluation of the starting configuration, for comparison
+    pub baseline: Evaluation,
+    /// Number of configurations simulated
+    pub evaluated: usize,
+}
+
+/// Offline tuner of the safe-cell thresholds, built on the simulator
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    /// Configuration whose thresholds are tuned, the other fields are kept
+    pub base: CiucConfig,
+    /// Environments in which each configuration is simulated
+    pub environments: BenchGrid,
+    pub space: SearchSpace,
+    pub objective: Objective,
+    pub method: SearchMethod,
+    /// Seed of the search (the simulations use the seeds of the grid)
+    pub seed: u64,
+}
+
+impl Tuner {
+    ///Function that creates a tuner with the default search space and objective
+    pub fn new(base: CiucConfig, environments: BenchGrid, method: SearchMethod) -> Self {
+        Tuner {
+            base,
+            environments,
+            space: SearchSpace::default(),
+            objective: Objective::default(),
+            method,
+            seed: 0,
+        }
+    }
+
+    ///Function that simulates a configuration in every environment
+    pub fn evaluate(&self, config: &CiucConfig) -> Result<Evaluation, String> {
+        let report = run_benchmark(
+            &[BenchPolicy::new("candidate", config.clone())],
+            &self.environments,
+        )?;
+        let runs: u64 = report.rows.iter().map(|r| r.runs).sum();
+        let n = runs.max(1) as f64;
+        // The rows are means over their runs, weight them back
+        let total = |f: fn(&BenchRow) -> f64| {
+            report
+                .rows
+                .iter()
+                .map(|r| f(r) * r.runs as f64)
+                .sum::<f64>()
+                / n
+        };
+        let survival_rate = total(|r| r.survival_rate);
+        let carbon_delivered = total(|r| r.carbon_delivered);
+        let sunrays_wasted = total(|r| r.sunrays_wasted);
+
+        Ok(Evaluation {
+            survival_rate,
+            carbon_delivered,
+            sunrays_wasted,
+            score: self
+                .objective
+                .score(survival_rate, carbon_delivered, sunrays_wasted),
+        })
+    }
+
+    fn candidate(&self, thresholds: SafeCellsConfig) -> CiucConfig {
+        CiucConfig {
+            safe_cells: SafeCellsConfig {
+                // Not searched, kept from the base
+                cells_per_extra_explorer: self.base.safe_cells.cells_per_extra_explorer,
+                ..thresholds
+            },
+            ..self.base.clone()
+        }
+    }
+
+    ///Function that searches the best thresholds, the base configuration is returned if nothing beats it
+    pub fn run(&self) -> Result<TuneResult, String> {
+        let mut rng = SimRng::new(self.seed);
+        let baseline = self.evaluate(&self.base)?;
+        let mut best = (self.base.clone(), baseline);
+        let mut evaluated = 1;
+
+        let mut consider = |thresholds: SafeCellsConfig,
+                            best: &mut (CiucConfig, Evaluation)|
+         -> Result<Evaluation, String> {
+            let config = self.candidate(thresholds);
+            let evaluation = self.evaluate(&config)?;
+            evaluated += 1;
+            if evaluation.score > best.1.score {
+                *best = (config, evaluation);
+            }
+            Ok(evaluation)
+        };
+
+        match self.method {
+            SearchMethod::Grid { steps } => {
+                for point in self.space.grid(steps) {
+                    consider(point, &mut best)?;
+                }
+            }
+            SearchMethod::Random { samples } => {
+                for _ in 0..samples {
+                    consider(self.space.sample(&mut rng), &mut best)?;
+                }
+            }
+            SearchMethod::Evolutionary {
+                population,
+                generations,
+            } => {
+                let population = population.max(2);
+                let mut scored = Vec::new();
+                for _ in 0..population {
+                    let point = self.space.sample(&mut rng);
+                    scored.push((point, consider(point, &mut best)?.score));
+                }
+                for _ in 0..generations {
+                    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+                    scored.truncate(population / 2);
+                    for i in 0..population - scored.len() {
+                        let parent = scored[i % scored.len()].0;
+                        let child = self.space.mutate(&parent, &mut rng);
+                        scored.push((child, consider(child, &mut best)?.score));
+                    }
+                }
+            }
+        }
+
+        Ok(TuneResult {
+            config: best.0,
+            evaluation: best.1,
+            baseline,
+            evaluated,
+        })
+    }
+}
+
+impl TuneResult {
+    ///Function that returns the recommended configuration as JSON, ready to be loaded with serde
+    pub fn config_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.config)
+            .map_err(|e| format!("Can't encode the configuration: {}", e))
+    }
+}
@@ -0,0 +1,115 @@
+use crate::CiucAI;
+use crate::ciuc::logging::LogCategory;
+use common_game::components::planet::PlanetState;
+use common_game::components::resource::{Carbon, Generator};
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+
+/// Bounded stock of carbon generated in advance, given to the explorers before using the cells
+pub(crate) struct CarbonStockpile {
+    items: Vec<Carbon>,
+    capacity: usize,
+}
+
+impl CarbonStockpile {
+    pub(crate) fn new(capacity: usize) -> Self {
+        CarbonStockpile {
+            items: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+
+    /// Returns the carbon back if the stockpile is full
+    pub(crate) fn push(&mut self, carbon: Carbon) -> Result<(), Carbon> {
+        if self.is_full() {
+            Err(carbon)
+        } else {
+            self.items.push(carbon);
+            Ok(())
+        }
+    }
+
+    pub(crate) fn take(&mut self) -> Option<Carbon> {
+        self.items.pop()
+    }
+}
+
+impl CiucAI {
+    ///Function for adding carbon to the stockpile, returns the carbon back if the stockpile is full
+    pub(crate) fn stock_carbon(&mut self, carbon: Carbon) -> Result<(), Carbon> {
+        self.stockpile_mut().push(carbon)?;
+        let stocked = self.stockpile().len() as u64;
+        self.metrics_handle().record(|m| m.carbon_stocked = stocked);
+        Ok(())
+    }
+
+    ///Function that takes carbon from the stockpile, if any
+    pub(crate) fn take_stocked_carbon(&mut self) -> Option<Carbon> {
+        let carbon = self.stockpile_mut().take()?;
+        let stocked = self.stockpile().len() as u64;
+        self.metrics_handle().record(|m| m.carbon_stocked = stocked);
+        Some(carbon)
+    }
+
+    ///Function for filling the stockpile with the charged cells above the safe target
+    pub(crate) fn fill_stockpile(&mut self, planet_state: &mut PlanetState, generator: &Generator) {
+        if !self.prefill_stockpile() {
+            return;
+        }
+
+        let safe_cells = self.current_safe_cells(planet_state);
+        let mut generated = 0;
+        while !self.stockpile().is_full()
+            && planet_state.cells_iter().filter(|c| c.is_charged()).count() as u32 > safe_cells
+        {
+            let carbon = match planet_state.full_cell() {
+                Some((cell, _)) => generator.make_carbon(cell),
+                None => Err("Should have found a charged cell, but didn't".to_string()),
+            };
+            match carbon {
+                Ok(carbon) => {
+                    // Can't fail, the stockpile is not full
+                    let _ = self.stock_carbon(carbon);
+                    generated += 1;
+                }
+                Err(e) => {
+                    self.log(
+                        LogCategory::Action,
+                        Some(Participant::new(ActorType::User, planet_state.id())),
+                        None,
+                        EventType::InternalPlanetAction,
+                        Channel::Error,
+                        [("message", e)],
+                    );
+                    return;
+                }
+            }
+        }
+
+        if generated > 0 {
+            self.log(
+                LogCategory::Action,
+                Some(Participant::new(ActorType::User, planet_state.id())),
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Info,
+                [(
+                    "message",
+                    format!(
+                        "Stocked {} carbon, the stockpile has {} carbon",
+                        generated,
+                        self.stockpile().len()
+                    ),
+                )],
+            );
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::CiucAI;
+use crate::ciuc::AIState;
+use crate::ciuc::carbon::{SafeCells, SafeCellsStage};
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::policy::Observation;
+use common_game::components::planet::PlanetState;
+
+/// Moment at which the strategy is asked whether to build a rocket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RocketTrigger {
+    Sunray,            // A cell has just been charged
+    AsteroidDeflected, // The rocket has just been used
+    AvoidSunrayWaste,  // The cells are full and a sunray is coming
+}
+
+/// Decisions of the AI: the handlers, the logging and the protocol stay the same for every strategy
+pub trait CiucStrategy: Send {
+    /// Charged cells to preserve before generating carbon (the AI adds its margin afterwards)
+    fn safe_cells(&self, observation: &Observation, config: &CiucConfig) -> SafeCells;
+
+    /// True to build a rocket, if the planet has none and a cell is charged
+    fn build_rocket(&self, observation: &Observation, trigger: RocketTrigger) -> bool;
+
+    /// State after a sunray or a deflected asteroid
+    fn next_state(&self, observation: &Observation, config: &CiucConfig) -> AIState;
+}
+
+/// Strategy of the Ciuc AI: safe until the estimates are usable, then statistic while the sunrays are more frequent than the asteroids
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultStrategy;
+
+impl CiucStrategy for DefaultStrategy {
+    fn safe_cells(&self, observation: &Observation, config: &CiucConfig) -> SafeCells {
+        let thresholds = config.safe_cells;
+        let periodicity = config.periodicity.unwrap_or_default();
+        let cell_count = observation.cell_count;
+        match observation.state {
+            AIState::SafeState => SafeCells::new(thresholds.safe_cells, cell_count),
+
+            AIState::StatisticState => {
+                // An event never received counts as received long ago
+                // If a sunray is expected soon, we can generate faster (the safe cell will return immediately)
+                // A periodic event is predicted, otherwise the time is compared with fractions of the mean interval
+                let sunray_imminent = match observation.until_next_sunray_ms {
+                    Some(until) => until <= periodicity.sunray_lead_ms,
+                    None => observation.since_last_sunray_ms.is_none_or(|t| {
+                        t as f64
+                            > thresholds.sunray_imminent_threshold * observation.estimate_sunray_ms
+                    }),
+                };
+                // If the asteroid is far away (less than half the estimated time has passed)
+                let asteroid_far = match observation.until_next_asteroid_ms {
+                    Some(until) => until > periodicity.asteroid_lead_ms,
+                    None => observation.since_last_asteroid_ms.is_some_and(|t| {
+                        (t as f64)
+                            < thresholds.asteroid_far_threshold * observation.estimate_asteroid_ms
+                    }),
+                };
+                // Generate less quickly when the asteroid is near
+                let asteroid_near = if asteroid_far {
+                    0
+                } else {
+                    thresholds.safe_cells_near_asteroid as i64
+                        - thresholds.safe_cells_far_asteroid as i64
+                };
+                let extra_explorers = observation.explorers.saturating_sub(1) as i64;
+
+                let mut safe_cells = SafeCells::new(thresholds.safe_cells_far_asteroid, cell_count)
+                    .adjust(SafeCellsStage::AsteroidNear, asteroid_near)
+                    .adjust(SafeCellsStage::SunrayImminent, -(sunray_imminent as i64))
+                    .adjust(
+                        SafeCellsStage::ExplorerDemand,
+                        thresholds.cells_per_extra_explorer as i64 * extra_explorers,
+                    );
+                safe_cells.asteroid_far = Some(asteroid_far);
+                safe_cells
+            }
+        }
+    }
+
+    fn build_rocket(&self, _observation: &Observation, _trigger: RocketTrigger) -> bool {
+        true // The rocket is always rebuilt as soon as possible
+    }
+
+    fn next_state(&self, observation: &Observation, config: &CiucConfig) -> AIState {
+        match observation.state {
+            // Return to safe zone if the asteroid threat is greater than sunray opportunity
+            AIState::StatisticState
+                if observation.estimate_asteroid_ms < observation.estimate_sunray_ms =>
+            {
+                AIState::SafeState
+            }
+            // Transition to StatisticState if enough data is collected and asteroid threat is less than sunray opportunity
+            AIState::SafeState
+                if config.statistic_state
+                    && observation.count_asteroids >= 3
+                    && observation.count_sunrays >= 3
+                    && observation.estimate_asteroid_ms >= observation.estimate_sunray_ms =>
+            {
+                AIState::StatisticState
+            }
+            state => state,
+        }
+    }
+}
+
+impl CiucAI {
+    ///Function that returns true if the strategy wants a rocket now
+    pub(crate) fn wants_rocket(&self, planet_state: &PlanetState, trigger: RocketTrigger) -> bool {
+        let observation = self.observation(planet_state, self.now());
+        self.strategy().build_rocket(&observation, trigger)
+    }
+
+    ///Function for building a rocket if the strategy wants it
+    pub(crate) fn try_build_rocket(
+        &self,
+        planet_state: &mut PlanetState,
+        trigger: RocketTrigger,
+    ) -> Result<(), String> {
+        if !self.wants_rocket(planet_state, trigger) {
+            return Err("The strategy chose not to build a rocket".to_string());
+        }
+        self.build_rocket(planet_state)
+    }
+}
@@ -0,0 +1,60 @@
+use crate::ciuc::carbon::SafeCellsConfig;
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::simulation::{SimEventKind, Simulation};
+use crate::ciuc::testing::invariants::check_invariants;
+
+/// Bytes decoded into the configuration, before the events
+const CONFIG_BYTES: usize = 6;
+/// Virtual time between two events for each unit of the gap byte
+const GAP_UNIT_MS: u64 = 16;
+
+///Function that decodes arbitrary bytes into a simulation: the first bytes choose the configuration
+///(thresholds even above the number of cells), then every pair of bytes is an event and the time before it
+pub fn decode_simulation(data: &[u8]) -> Simulation {
+    let byte = |i: usize| data.get(i).copied().unwrap_or(0);
+    let flags = byte(5);
+    let config = CiucConfig {
+        safe_cells: SafeCellsConfig {
+            safe_cells: (byte(0) % 8) as u32,
+            safe_cells_far_asteroid: (byte(1) % 8) as u32,
+            safe_cells_near_asteroid: (byte(2) % 8) as u32,
+            sunray_imminent_threshold: byte(3) as f64 / 64.0,
+            asteroid_far_threshold: byte(4) as f64 / 64.0,
+            cells_per_extra_explorer: (flags >> 5) as i32 - 3,
+        },
+        avoid_sunray_waste: flags & 1 != 0,
+        prefill_stockpile: flags & 2 != 0,
+        statistic_state: flags & 4 == 0,
+        stockpile_capacity: (flags >> 3 & 3) as usize,
+        decision_history: 10_000,
+        ..Default::default()
+    };
+
+    let mut simulation = Simulation::new(config, 0);
+    let mut at = 0;
+    for pair in data.get(CONFIG_BYTES..).unwrap_or_default().chunks_exact(2) {
+        let (event, gap) = (pair[0], pair[1]);
+        at += gap as u64 * GAP_UNIT_MS;
+        let explorer_id = (event >> 3) as u32 % 4;
+        let kind = match event % 8 {
+            0 | 1 => SimEventKind::Sunray,
+            2 => SimEventKind::Asteroid,
+            3 => SimEventKind::CarbonRequest { explorer_id },
+            4 => SimEventKind::AvailableCellsRequest { explorer_id },
+            5 => SimEventKind::OtherExplorerRequest { explorer_id },
+            6 => SimEventKind::ExplorerArrival { explorer_id },
+            _ => SimEventKind::ExplorerDeparture { explorer_id },
+        };
+        simulation = simulation.event(at, kind);
+    }
+    simulation.duration_ms = at;
+    simulation
+}
+
+///Function that runs the simulation decoded from the bytes and checks the invariants,
+///an error means the planet failed to answer or broke an invariant
+pub fn fuzz_events(data: &[u8]) -> Result<(), String> {
+    let simulation = decode_simulation(data);
+    let report = simulation.run()?;
+    check_invariants(&simulation.config, &report)
+}
@@ -0,0 +1,138 @@
+use crate::ciuc::AIState;
+use crate::ciuc::config::CiucConfig;
+use crate::ciuc::decision::CarbonDecision;
+use crate::ciuc::events::{SimEventKind, SimOutcome};
+use crate::ciuc::simulation::{SimReport, TimelineEntry};
+
+///Function that checks, with the cells of the timeline, that carbon is made from a cell only when the charged cells
+///are above the fewest safe cells the configuration allows in the recorded state, and that it uses one cell
+pub fn no_carbon_at_or_below_safe_target(
+    config: &CiucConfig,
+    report: &SimReport,
+) -> Result<(), String> {
+    for (entry, decision, charged_before) in carbon_requests(report)? {
+        if !decision.granted || decision.from_stock {
+            continue;
+        }
+        let lowest = lowest_safe_cells(config, decision.state, entry.cell_count);
+        if charged_before <= lowest {
+            return Err(format!(
+                "Carbon generated at {}ms with {} charged cells in {:?}, which keeps at least {}",
+                entry.at_ms, charged_before, decision.state, lowest
+            ));
+        }
+        if entry.charged_cells + 1 != charged_before {
+            return Err(format!(
+                "Carbon generated at {}ms, but the charged cells went from {} to {}",
+                entry.at_ms, charged_before, entry.charged_cells
+            ));
+        }
+    }
+    Ok(())
+}
+
+///Function that checks that the advertised cells are not more than the charged cells above the safe target
+///plus the stocked carbon, and that a carbon request right after one is granted
+pub fn available_cells_not_overstated(report: &SimReport) -> Result<(), String> {
+    for (i, entry) in report.timeline.iter().enumerate() {
+        let SimOutcome::AvailableCells(available) = entry.outcome else {
+            continue;
+        };
+        let obtainable = entry
+            .charged_cells
+            .saturating_sub(entry.safe_cells as usize) as u64
+            + entry.stocked_carbon;
+        if available as u64 > obtainable {
+            return Err(format!(
+                "{} cells advertised at {}ms with {} charged, {} safe and {} carbon stocked",
+                available, entry.at_ms, entry.charged_cells, entry.safe_cells, entry.stocked_carbon
+            ));
+        }
+        if let Some(next) = report.timeline.get(i + 1)
+            && available > 0
+            && next.at_ms == entry.at_ms
+            && matches!(next.kind, SimEventKind::CarbonRequest { .. })
+            && next.outcome != SimOutcome::CarbonGranted
+        {
+            return Err(format!(
+                "{} cells advertised at {}ms, but the carbon was refused",
+                available, entry.at_ms
+            ));
+        }
+    }
+    Ok(())
+}
+
+///Function that checks that in 'SafeState' the explorers never take the planet below the configured safe cells
+pub fn safe_state_keeps_safe_cells(config: &CiucConfig, report: &SimReport) -> Result<(), String> {
+    for (entry, decision, charged_before) in carbon_requests(report)? {
+        let safe =
+            lowest_safe_cells(config, AIState::SafeState, entry.cell_count).min(charged_before);
+        if decision.state == AIState::SafeState && entry.charged_cells < safe {
+            return Err(format!(
+                "{} charged cells after a request at {}ms, {} before",
+                entry.charged_cells, entry.at_ms, charged_before
+            ));
+        }
+    }
+    Ok(())
+}
+
+///Function that pairs the carbon requests of the timeline with their decision and the charged cells before them
+fn carbon_requests(
+    report: &SimReport,
+) -> Result<Vec<(&TimelineEntry, &CarbonDecision, usize)>, String> {
+    // Only the most recent decisions are kept
+    let skipped = (report.stats.carbon_requests as usize).saturating_sub(report.decisions.len());
+    let mut decisions = report.decisions.iter();
+    let mut requests = Vec::new();
+    let mut seen = 0;
+    let mut charged_before = 0;
+    for entry in &report.timeline {
+        if let SimEventKind::CarbonRequest { .. } = entry.kind {
+            seen += 1;
+            if seen > skipped {
+                let decision = decisions.next().ok_or("Missing carbon decision")?;
+                requests.push((entry, decision, charged_before));
+            }
+        }
+        charged_before = entry.charged_cells;
+    }
+    Ok(requests)
+}
+
+///Function that returns the fewest safe cells the configured thresholds allow in a state,
+///bounding every stage to the cells of the planet like the safe cells pipeline
+fn lowest_safe_cells(config: &CiucConfig, state: AIState, cell_count: usize) -> usize {
+    let bound = |value: i64| value.clamp(0, cell_count as i64);
+    let thresholds = config.safe_cells;
+    let value = match state {
+        // A policy is kept at or above the threshold too
+        AIState::SafeState => bound(thresholds.safe_cells as i64),
+        // The policy replaces the thresholds, and a negative demand has no floor
+        AIState::StatisticState
+            if config.q_learning.is_some() || thresholds.cells_per_extra_explorer < 0 =>
+        {
+            0
+        }
+        AIState::StatisticState => {
+            let far = bound(thresholds.safe_cells_far_asteroid as i64);
+            let near = bound(
+                far + thresholds.safe_cells_near_asteroid as i64
+                    - thresholds.safe_cells_far_asteroid as i64,
+            );
+            // A sunray may be imminent
+            bound(far.min(near) - 1)
+        }
+    };
+    // The adaptive margin may lower the threshold
+    let lowest_margin = config.adaptive_margin.map_or(0, |m| m.min_margin.min(0));
+    bound(value + lowest_margin as i64) as usize
+}
+
+///Function that checks every invariant on the result of a simulation
+pub fn check_invariants(config: &CiucConfig, report: &SimReport) -> Result<(), String> {
+    no_carbon_at_or_below_safe_target(config, report)?;
+    available_cells_not_overstated(report)?;
+    safe_state_keeps_safe_cells(config, report)
+}
@@ -0,0 +1,10 @@
+mod fuzz;
+mod invariants;
+mod orchestrator;
+
+pub use fuzz::{decode_simulation, fuzz_events};
+pub use invariants::{
+    available_cells_not_overstated, check_invariants, no_carbon_at_or_below_safe_target,
+    safe_state_keeps_safe_cells,
+};
+pub use orchestrator::{CombineResult, MockExplorer, TestOrchestrator};
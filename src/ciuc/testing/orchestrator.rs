@@ -0,0 +1,326 @@
+use crate::CiucAI;
+use crate::ciuc::create_planet::create_planet_with_ai;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::DummyPlanetState;
+use common_game::components::resource::{
+    BasicResource, BasicResourceType, ComplexResource, ComplexResourceRequest, ComplexResourceType,
+    GenericResource,
+};
+use common_game::components::rocket::Rocket;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use crossbeam_channel::{Receiver, Sender};
+use std::collections::HashSet;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Time waited for an answer of the planet, unless changed with `with_timeout`
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Stand-in for the orchestrator in the tests: owns the channels and the planet thread,
+/// every helper sends one message and checks that the planet gives the expected answer
+pub struct TestOrchestrator {
+    planet_id: u32,
+    to_planet: Sender<OrchestratorToPlanet>,
+    from_planet: Receiver<PlanetToOrchestrator>,
+    explorer_to_planet: Sender<ExplorerToPlanet>,
+    timeout: Duration,
+    handle: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl TestOrchestrator {
+    ///Function that creates the planet with the given AI and runs it on its own thread, still stopped
+    pub fn new(planet_id: u32, ai: CiucAI) -> Self {
+        let (to_planet, rx_orchestrator) = crossbeam_channel::unbounded();
+        let (tx_orchestrator, from_planet) = crossbeam_channel::unbounded();
+        let (explorer_to_planet, rx_explorer) = crossbeam_channel::unbounded();
+        let mut planet =
+            create_planet_with_ai(rx_orchestrator, tx_orchestrator, rx_explorer, planet_id, ai);
+        let handle = thread::spawn(move || planet.run());
+        TestOrchestrator {
+            planet_id,
+            to_planet,
+            from_planet,
+            explorer_to_planet,
+            timeout: DEFAULT_TIMEOUT,
+            handle: Some(handle),
+        }
+    }
+
+    ///Function that creates the planet and starts its AI
+    pub fn started(planet_id: u32, ai: CiucAI) -> Result<Self, String> {
+        let orchestrator = TestOrchestrator::new(planet_id, ai);
+        orchestrator.start_ai()?;
+        Ok(orchestrator)
+    }
+
+    ///Function that changes how long the planet's answers are awaited
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn planet_id(&self) -> u32 {
+        self.planet_id
+    }
+
+    ///Function that returns the channel on which the explorers send their requests
+    pub fn explorer_channel(&self) -> Sender<ExplorerToPlanet> {
+        self.explorer_to_planet.clone()
+    }
+
+    ///Function that sends a message and waits for the answer, which must come from this planet
+    pub fn send(&self, msg: OrchestratorToPlanet) -> Result<PlanetToOrchestrator, String> {
+        self.to_planet
+            .send(msg)
+            .map_err(|_| format!("Planet {} disconnected", self.planet_id))?;
+        let answer = self
+            .from_planet
+            .recv_timeout(self.timeout)
+            .map_err(|e| format!("Planet {} didn't answer: {}", self.planet_id, e))?;
+        if answer.planet_id() != self.planet_id {
+            return Err(format!(
+                "Planet {} answered with the id {}",
+                self.planet_id,
+                answer.planet_id()
+            ));
+        }
+        Ok(answer)
+    }
+
+    ///Function that returns an error if the planet sent something nobody asked for
+    pub fn assert_no_pending(&self) -> Result<(), String> {
+        match self.from_planet.try_recv() {
+            Ok(msg) => Err(format!("Unexpected message from the planet: {:?}", msg)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    pub fn start_ai(&self) -> Result<(), String> {
+        match self.send(OrchestratorToPlanet::StartPlanetAI)? {
+            PlanetToOrchestrator::StartPlanetAIResult { .. } => Ok(()),
+            other => Err(format!("Unexpected answer to the start: {:?}", other)),
+        }
+    }
+
+    pub fn stop_ai(&self) -> Result<(), String> {
+        match self.send(OrchestratorToPlanet::StopPlanetAI)? {
+            PlanetToOrchestrator::StopPlanetAIResult { .. } => Ok(()),
+            other => Err(format!("Unexpected answer to the stop: {:?}", other)),
+        }
+    }
+
+    pub fn send_sunray(&self) -> Result<(), String> {
+        match self.send(OrchestratorToPlanet::Sunray(Sunray::default()))? {
+            PlanetToOrchestrator::SunrayAck { .. } => Ok(()),
+            other => Err(format!("Unexpected answer to a sunray: {:?}", other)),
+        }
+    }
+
+    ///Function that sends an asteroid, returns the rocket used to deflect it (`None` if the planet is destroyed)
+    pub fn send_asteroid(&self) -> Result<Option<Rocket>, String> {
+        match self.send(OrchestratorToPlanet::Asteroid(Asteroid::default()))? {
+            PlanetToOrchestrator::AsteroidAck { rocket, .. } => Ok(rocket),
+            other => Err(format!("Unexpected answer to an asteroid: {:?}", other)),
+        }
+    }
+
+    pub fn request_state(&self) -> Result<DummyPlanetState, String> {
+        match self.send(OrchestratorToPlanet::InternalStateRequest)? {
+            PlanetToOrchestrator::InternalStateResponse { planet_state, .. } => Ok(planet_state),
+            other => Err(format!(
+                "Unexpected answer to the internal state request: {:?}",
+                other
+            )),
+        }
+    }
+
+    ///Function that registers an explorer, the planet will answer it on 'sender'
+    pub fn add_explorer(
+        &self,
+        explorer_id: u32,
+        sender: Sender<PlanetToExplorer>,
+    ) -> Result<(), String> {
+        match self.send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: sender,
+        })? {
+            PlanetToOrchestrator::IncomingExplorerResponse {
+                explorer_id: id,
+                res,
+                ..
+            } if id == explorer_id => res,
+            other => Err(format!(
+                "Unexpected answer to the explorer arrival: {:?}",
+                other
+            )),
+        }
+    }
+
+    pub fn remove_explorer(&self, explorer_id: u32) -> Result<(), String> {
+        match self.send(OrchestratorToPlanet::OutgoingExplorerRequest { explorer_id })? {
+            PlanetToOrchestrator::OutgoingExplorerResponse {
+                explorer_id: id,
+                res,
+                ..
+            } if id == explorer_id => res,
+            other => Err(format!(
+                "Unexpected answer to the explorer departure: {:?}",
+                other
+            )),
+        }
+    }
+
+    ///Function that kills the planet and waits for its thread, returning the result of `Planet::run`
+    pub fn kill(mut self) -> Result<(), String> {
+        match self.send(OrchestratorToPlanet::KillPlanet)? {
+            PlanetToOrchestrator::KillPlanetResult { .. } => {}
+            other => return Err(format!("Unexpected answer to the kill: {:?}", other)),
+        }
+        match self.handle.take().map(|h| h.join()) {
+            Some(Ok(res)) => res,
+            Some(Err(_)) => Err(format!("The thread of planet {} panicked", self.planet_id)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TestOrchestrator {
+    fn drop(&mut self) {
+        // A failed test leaves the planet thread waiting for messages
+        if self.handle.is_some() {
+            let _ = self.to_planet.send(OrchestratorToPlanet::KillPlanet);
+        }
+    }
+}
+
+/// Explorer visiting the planet in the tests: registered through the orchestrator protocol,
+/// it receives the planet's answers on its own channel
+pub struct MockExplorer {
+    explorer_id: u32,
+    to_planet: Sender<ExplorerToPlanet>,
+    from_planet: Receiver<PlanetToExplorer>,
+    timeout: Duration,
+}
+
+/// Answer to a combination request, as sent by the planet
+pub type CombineResult = Result<ComplexResource, (String, GenericResource, GenericResource)>;
+
+impl MockExplorer {
+    ///Function that registers a new explorer on the planet driven by 'orchestrator'
+    pub fn arrive(orchestrator: &TestOrchestrator, explorer_id: u32) -> Result<Self, String> {
+        let (sender, from_planet) = crossbeam_channel::unbounded();
+        orchestrator.add_explorer(explorer_id, sender)?;
+        Ok(MockExplorer {
+            explorer_id,
+            to_planet: orchestrator.explorer_channel(),
+            from_planet,
+            timeout: orchestrator.timeout,
+        })
+    }
+
+    ///Function that unregisters the explorer from the planet
+    pub fn leave(self, orchestrator: &TestOrchestrator) -> Result<(), String> {
+        orchestrator.remove_explorer(self.explorer_id)
+    }
+
+    pub fn explorer_id(&self) -> u32 {
+        self.explorer_id
+    }
+
+    ///Function that sends a request and waits for the answer on the explorer's channel
+    pub fn send(&self, msg: ExplorerToPlanet) -> Result<PlanetToExplorer, String> {
+        self.to_planet
+            .send(msg)
+            .map_err(|_| "Planet disconnected".to_string())?;
+        self.from_planet.recv_timeout(self.timeout).map_err(|e| {
+            format!(
+                "The planet didn't answer explorer {}: {}",
+                self.explorer_id, e
+            )
+        })
+    }
+
+    ///Function that returns an error if the planet sent something nobody asked for
+    pub fn assert_no_pending(&self) -> Result<(), String> {
+        match self.from_planet.try_recv() {
+            Ok(msg) => Err(format!("Unexpected message from the planet: {:?}", msg)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    pub fn request_supported_resources(&self) -> Result<HashSet<BasicResourceType>, String> {
+        let explorer_id = self.explorer_id;
+        match self.send(ExplorerToPlanet::SupportedResourceRequest { explorer_id })? {
+            PlanetToExplorer::SupportedResourceResponse { resource_list } => Ok(resource_list),
+            other => Err(format!(
+                "Unexpected answer to a supported resource request: {:?}",
+                other
+            )),
+        }
+    }
+
+    pub fn request_supported_combinations(&self) -> Result<HashSet<ComplexResourceType>, String> {
+        let explorer_id = self.explorer_id;
+        match self.send(ExplorerToPlanet::SupportedCombinationRequest { explorer_id })? {
+            PlanetToExplorer::SupportedCombinationResponse { combination_list } => {
+                Ok(combination_list)
+            }
+            other => Err(format!(
+                "Unexpected answer to a supported combination request: {:?}",
+                other
+            )),
+        }
+    }
+
+    ///Function that asks for a resource, returns `None` if the planet refused
+    pub fn request_resource(
+        &self,
+        resource: BasicResourceType,
+    ) -> Result<Option<BasicResource>, String> {
+        let explorer_id = self.explorer_id;
+        match self.send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource,
+        })? {
+            PlanetToExplorer::GenerateResourceResponse { resource } => Ok(resource),
+            other => Err(format!(
+                "Unexpected answer to a resource request: {:?}",
+                other
+            )),
+        }
+    }
+
+    ///Function that asks for carbon, returns `None` if the planet refused
+    pub fn request_carbon(&self) -> Result<Option<BasicResource>, String> {
+        self.request_resource(BasicResourceType::Carbon)
+    }
+
+    pub fn request_combination(
+        &self,
+        msg: ComplexResourceRequest,
+    ) -> Result<CombineResult, String> {
+        let explorer_id = self.explorer_id;
+        match self.send(ExplorerToPlanet::CombineResourceRequest { explorer_id, msg })? {
+            PlanetToExplorer::CombineResourceResponse { complex_response } => Ok(complex_response),
+            other => Err(format!(
+                "Unexpected answer to a combination request: {:?}",
+                other
+            )),
+        }
+    }
+
+    pub fn request_available_cells(&self) -> Result<u32, String> {
+        let explorer_id = self.explorer_id;
+        match self.send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id })? {
+            PlanetToExplorer::AvailableEnergyCellResponse { available_cells } => {
+                Ok(available_cells)
+            }
+            other => Err(format!(
+                "Unexpected answer to an available cells request: {:?}",
+                other
+            )),
+        }
+    }
+}
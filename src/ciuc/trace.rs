@@ -0,0 +1,154 @@
+use crate::CiucAI;
+use crate::ciuc::logging::LogCategory;
+use crate::ciuc::events::{SimEventKind, SimOutcome};
+use common_game::components::resource::BasicResourceType;
+use common_game::logging::Participant;
+use common_game::logging::{ActorType, Channel, EventType};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Line of a trace: an incoming event, the time of the AI clock when it arrived and how the AI reacted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceRecord {
+    pub at_ms: i64,
+    pub event: SimEventKind,
+    pub outcome: SimOutcome,
+}
+
+///Function for reading a trace written by the AI, one JSON record per line
+pub fn read_trace(path: impl AsRef<Path>) -> Result<Vec<TraceRecord>, String> {
+    let path = path.as_ref();
+    let file =
+        File::open(path).map_err(|e| format!("Can't read the trace {}: {}", path.display(), e))?;
+    let mut records = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Can't read the trace {}: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|e| {
+            format!(
+                "Invalid record at line {} of {}: {}",
+                i + 1,
+                path.display(),
+                e
+            )
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Writer of the trace, each record is flushed so that the trace survives a crash of the galaxy
+pub(crate) struct TraceRecorder {
+    writer: BufWriter<File>,
+}
+
+impl TraceRecorder {
+    pub(crate) fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Can't create the trace {}: {}", path.display(), e))?;
+        Ok(TraceRecorder {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub(crate) fn write(&mut self, record: &TraceRecord) -> Result<(), String> {
+        let line = serde_json::to_string(record)
+            .map_err(|e| format!("Can't encode the trace record: {}", e))?;
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| format!("Can't write the trace: {}", e))
+    }
+}
+
+///Function that returns the event recorded for an explorer message
+pub(crate) fn explorer_event(msg: &ExplorerToPlanet) -> SimEventKind {
+    let explorer_id = msg.explorer_id();
+    match msg {
+        ExplorerToPlanet::GenerateResourceRequest {
+            resource: BasicResourceType::Carbon,
+            ..
+        } => SimEventKind::CarbonRequest { explorer_id },
+        ExplorerToPlanet::AvailableEnergyCellRequest { .. } => {
+            SimEventKind::AvailableCellsRequest { explorer_id }
+        }
+        _ => SimEventKind::OtherExplorerRequest { explorer_id },
+    }
+}
+
+///Function that returns the outcome recorded for the answer to an explorer
+pub(crate) fn explorer_outcome(
+    event: SimEventKind,
+    response: &Option<PlanetToExplorer>,
+) -> SimOutcome {
+    match (event, response) {
+        (
+            SimEventKind::CarbonRequest { .. },
+            Some(PlanetToExplorer::GenerateResourceResponse { resource: Some(_) }),
+        ) => SimOutcome::CarbonGranted,
+        (SimEventKind::CarbonRequest { .. }, _) => SimOutcome::CarbonRefused,
+        (_, Some(PlanetToExplorer::AvailableEnergyCellResponse { available_cells })) => {
+            SimOutcome::AvailableCells(*available_cells)
+        }
+        _ => SimOutcome::Answered,
+    }
+}
+
+impl CiucAI {
+    ///Function for opening the trace file, if the recording is enabled
+    pub(crate) fn open_trace(&mut self, id: u32) {
+        let Some(path) = self.config().trace_path.clone() else {
+            return;
+        };
+        if self.trace_mut().is_some() {
+            return; // Restarted after a stop: keep appending to the same trace
+        }
+
+        let (channel, message) = match TraceRecorder::create(&path) {
+            Ok(recorder) => {
+                *self.trace_mut() = Some(recorder);
+                (
+                    Channel::Info,
+                    format!("Recording the events to {}", path.display()),
+                )
+            }
+            Err(e) => (Channel::Warning, e),
+        };
+        self.log(
+            LogCategory::Protocol,
+            Some(Participant::new(ActorType::User, id)),
+            None,
+            EventType::InternalPlanetAction,
+            channel,
+            [("message", message)],
+        );
+    }
+
+    ///Function for recording an event in the trace, if it's open
+    pub(crate) fn record_trace(&mut self, id: u32, event: SimEventKind, outcome: SimOutcome) {
+        let record = TraceRecord {
+            at_ms: self.now(),
+            event,
+            outcome,
+        };
+        let Some(recorder) = self.trace_mut() else {
+            return;
+        };
+        if let Err(e) = recorder.write(&record) {
+            // Stop recording: a trace with holes would replay wrong decisions
+            *self.trace_mut() = None;
+            self.log(
+                LogCategory::Protocol,
+                Some(Participant::new(ActorType::User, id)),
+                None,
+                EventType::InternalPlanetAction,
+                Channel::Warning,
+                [("message", e)],
+            );
+        }
+    }
+}
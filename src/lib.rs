@@ -1,5 +1,9 @@
 pub mod ciuc;
 
 pub use ciuc::CiucAI;
+pub use ciuc::CiucConfig;
 pub use ciuc::create_planet;
+pub use ciuc::create_planet_from_state;
+pub use ciuc::create_planet_with_ai;
+pub use ciuc::create_planet_with_config;
 pub use ciuc::update_ema;
@@ -1,8 +1,25 @@
+#![allow(clippy::assertions_on_constants)] // the original tests assert reaching an arm
+
+use std::sync::{Arc, Mutex, Once};
 use std::thread;
 use std::time::Duration;
 
-use ciuc_planet::ciuc::create_planet;
-use ciuc_planet::update_ema;
+use ciuc_planet::ciuc::testing::{
+    MockExplorer, TestOrchestrator, available_cells_not_overstated, decode_simulation, fuzz_events,
+    no_carbon_at_or_below_safe_target, safe_state_keeps_safe_cells,
+};
+use ciuc_planet::ciuc::simulation::{
+    BenchGrid, BenchPolicy, Objective, RandomStream, SearchMethod, SimEvent, SimEventKind, SimOutcome, SimReport,
+    SimRng, Simulation, Tuner, replay, run_benchmark, train_q_learning,
+};
+use ciuc_planet::ciuc::{
+    AIState, AdaptiveMarginConfig, CategoryLogConfig, CiucAIState, CiucStrategy, Clock, DefaultStrategy,
+    LogCategory, LogConfig, Observation, OutlierAction, OutlierConfig, PeriodicityConfig, Policy, PolicyFeedback, Prior, QLearningConfig, QLearningPolicy, RegimeChangeConfig, RocketTrigger, SafeCells, SafeCellsConfig, SafeCellsStage, Verbosity,
+    create_planet, read_trace, to_prometheus,
+};
+use ciuc_planet::{
+    CiucAI, CiucConfig, create_planet_with_ai, create_planet_with_config, update_ema,
+};
 use common_game::components::asteroid::Asteroid;
 use common_game::components::planet::Planet;
 use common_game::components::resource::{BasicResource, BasicResourceType};
@@ -37,6 +54,48 @@ fn create_mock_planet() -> (
     (planet, tx_orch_in, rx_orch_out, tx_expl_in)
 }
 
+// logger collecting every emitted event, so tests can check what the planet logs
+struct CaptureLogger {
+    records: Mutex<Vec<(log::Level, String)>>,
+}
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE_LOGGER: CaptureLogger = CaptureLogger {
+    records: Mutex::new(Vec::new()),
+};
+static CAPTURE_LOGGER_INIT: Once = Once::new();
+
+// returns the events logged so far by the planet with the given id
+fn captured_logs(planet_id: u32) -> Vec<(log::Level, String)> {
+    CAPTURE_LOGGER_INIT.call_once(|| {
+        log::set_logger(&CAPTURE_LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+    let sender = format!("id: {} }}", planet_id);
+    CAPTURE_LOGGER
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, line)| line.contains(&sender))
+        .cloned()
+        .collect()
+}
+
 //-----------------------Orchestrator to Planet-------------------------
 
 #[test]
@@ -843,6 +902,858 @@ fn test_revert_ai() {
     }
 }
 
+//-----------------------Logging-------------------------
+
+#[test] // with every category turned off only warnings and errors must be logged
+fn test_log_verbosity_keeps_errors() {
+    let planet_id = 40;
+    let _ = captured_logs(planet_id);
+
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    let config = CiucConfig {
+        logging: LogConfig {
+            default: CategoryLogConfig {
+                verbosity: Verbosity::Off,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut planet =
+        create_planet_with_config(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, config);
+
+    let explorer_id = 2;
+    let (tx_expl_local, rx_expl_local) = crossbeam_channel::unbounded();
+
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    tx_orch_in
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_expl_local,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // one sunray builds the rocket, so no charged cell is left for carbon
+    tx_orch_in
+        .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+        .unwrap();
+    let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource: BasicResourceType::Carbon,
+        })
+        .unwrap();
+    match rx_expl_local.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::GenerateResourceResponse { resource: None }) => {}
+        _ => panic!("I should not receive carbon"),
+    }
+
+    let logs = captured_logs(planet_id);
+    assert!(
+        logs.iter().all(|(level, _)| *level <= log::Level::Warn),
+        "Only warnings and errors should be logged: {:?}",
+        logs
+    );
+    assert!(
+        logs.iter().any(|(level, line)| *level == log::Level::Error
+            && line.contains("Didn't find any charged cell")),
+        "The refusal error should still be logged"
+    );
+
+    tx_orch_in.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    drop(tx_orch_in);
+    let _ = handle.join();
+}
+
+#[test] // every carbon request leaves a trace explaining the decision
+fn test_carbon_decision_trace() {
+    let planet_id = 41;
+    let _ = captured_logs(planet_id);
+
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    let ai = CiucAI::new();
+    let decisions = ai.decision_log();
+    let mut planet = create_planet_with_ai(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, ai);
+
+    let explorer_id = 2;
+    let (tx_expl_local, rx_expl_local) = crossbeam_channel::unbounded();
+
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    tx_orch_in
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_expl_local,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // two sunrays: one for the rocket and one charged cell, not enough in safe state
+    for _ in 0..2 {
+        tx_orch_in
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+    }
+    assert!(
+        decisions.last().is_none(),
+        "No carbon has been requested yet"
+    );
+
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource: BasicResourceType::Carbon,
+        })
+        .unwrap();
+    match rx_expl_local.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::GenerateResourceResponse { resource: None }) => {}
+        _ => panic!("I should not receive carbon"),
+    }
+
+    let decision = decisions
+        .last()
+        .expect("The decision should have been recorded");
+    assert_eq!(decision.explorer_id, explorer_id);
+    assert_eq!(decision.state, AIState::SafeState);
+    assert_eq!(decision.charged_cells, 1);
+    assert_eq!(decision.safe_cells, 3);
+    assert_eq!(decision.asteroid_far, None);
+    assert!(decision.since_last_sunray_ms.is_some());
+    assert!(decision.since_last_asteroid_ms.is_none());
+    assert!(!decision.granted);
+    assert!(
+        decision.refusal.unwrap().starts_with("Conserving energy"),
+        "The refusal reason should be recorded"
+    );
+
+    let logs = captured_logs(planet_id);
+    assert!(
+        logs.iter().any(|(level, line)| *level == log::Level::Error
+            && line.contains("Conserving energy")
+            && line.contains("\"safe_cells\": \"3\"")),
+        "The refusal should be logged with its trace"
+    );
+
+    tx_orch_in.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    drop(tx_orch_in);
+    let _ = handle.join();
+}
+
+//-----------------------Metrics-------------------------
+
+#[test] // the metrics count what happened to the planet
+fn test_metrics() {
+    let planet_id = 42;
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    // keep the wasted sunray
+    let ai = CiucAI::with_config(CiucConfig {
+        avoid_sunray_waste: false,
+        prefill_stockpile: false,
+        ..Default::default()
+    });
+    let metrics = ai.metrics();
+    let mut planet = create_planet_with_ai(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, ai);
+
+    let explorer_id = 2;
+    let (tx_expl_local, rx_expl_local) = crossbeam_channel::unbounded();
+
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    tx_orch_in
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_expl_local,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // 1 sunray for the rocket, 5 for the cells and the last one is wasted
+    for _ in 0..7 {
+        tx_orch_in
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+    }
+
+    // the rocket is used and rebuilt with a charged cell
+    tx_orch_in
+        .send(OrchestratorToPlanet::Asteroid(Asteroid::default()))
+        .unwrap();
+    let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+
+    // 4 charged cells: the first request is granted, the second one refused
+    for _ in 0..2 {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Carbon,
+            })
+            .unwrap();
+        let _ = rx_expl_local.recv_timeout(Duration::from_millis(200));
+    }
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.sunrays_received, 7);
+    assert_eq!(snapshot.sunrays_wasted, 1);
+    assert_eq!(snapshot.rockets_built, 2);
+    assert_eq!(snapshot.asteroids_survived, 1);
+    assert_eq!(snapshot.asteroids_fatal, 0);
+    assert_eq!(snapshot.carbon_generated.get(&explorer_id), Some(&1));
+    assert_eq!(snapshot.carbon_refused.get(&explorer_id), Some(&1));
+    assert_eq!(snapshot.sunray_interval_ms.count, 6);
+    assert_eq!(snapshot.time_in_statistic_state_ms, 0);
+
+    let exported = to_prometheus(&snapshot, planet_id);
+    assert!(exported.contains("ciuc_sunrays_wasted_total{planet=\"42\"} 1"));
+    assert!(exported.contains("ciuc_carbon_refused_total{planet=\"42\",explorer=\"2\"} 1"));
+    assert!(exported.contains("ciuc_sunray_interval_ms_count{planet=\"42\"} 6"));
+
+    tx_orch_in.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    drop(tx_orch_in);
+    let _ = handle.join();
+}
+
+#[test] // a sunray arriving on full cells is not wasted: a charged cell becomes carbon
+fn test_sunray_waste_avoided() {
+    let planet_id = 43;
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    // without prefilling the cells get full. The clock is stopped, so only the sunray charging
+    // the last cell frees one, for the next sunray
+    let ai = CiucAI::with_clock(
+        CiucConfig {
+            avoid_sunray_waste: true,
+            stockpile_capacity: 2,
+            prefill_stockpile: false,
+            ..Default::default()
+        },
+        Clock::manual(1_000_000),
+    );
+    let metrics = ai.metrics();
+    let decisions = ai.decision_log();
+    let mut planet = create_planet_with_ai(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, ai);
+
+    let explorer_id = 2;
+    let (tx_expl_local, rx_expl_local) = crossbeam_channel::unbounded();
+
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    tx_orch_in
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_expl_local,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // 1 sunray for the rocket, 5 for the cells (the last one frees a cell) and one that would be wasted
+    for _ in 0..7 {
+        tx_orch_in
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+    }
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.sunrays_wasted, 0);
+    assert_eq!(snapshot.sunrays_waste_avoided, 1);
+
+    // the carbon made from the freed cell is given to the explorer
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource: BasicResourceType::Carbon,
+        })
+        .unwrap();
+    match rx_expl_local.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::GenerateResourceResponse {
+            resource: Some(BasicResource::Carbon(_)),
+        }) => {}
+        _ => panic!("The stocked carbon should have been sent"),
+    }
+    assert!(decisions.last().unwrap().from_stock);
+
+    tx_orch_in.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    drop(tx_orch_in);
+    let _ = handle.join();
+}
+
+#[test] // the stockpile answers the explorers even right after an asteroid used a cell
+fn test_carbon_stockpile() {
+    let planet_id = 44;
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    let config = CiucConfig {
+        stockpile_capacity: 2,
+        prefill_stockpile: true,
+        ..Default::default()
+    };
+    let mut planet =
+        create_planet_with_config(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, config);
+
+    let explorer_id = 2;
+    let (tx_expl_local, rx_expl_local) = crossbeam_channel::unbounded();
+
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    tx_orch_in
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_expl_local,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // 1 sunray for the rocket, 3 safe cells and 2 cells turned into stocked carbon
+    for _ in 0..6 {
+        tx_orch_in
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+    }
+
+    // the rocket is rebuilt with a safe cell, leaving less than the safe cells
+    tx_orch_in
+        .send(OrchestratorToPlanet::Asteroid(Asteroid::default()))
+        .unwrap();
+    let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+
+    tx_expl
+        .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id })
+        .unwrap();
+    match rx_expl_local.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::AvailableEnergyCellResponse { available_cells }) => {
+            assert_eq!(available_cells, 0, "The stocked carbon is not an energy cell")
+        }
+        _ => panic!("No response was received for AvailableEnergyCellRequest"),
+    }
+
+    // two requests are served by the stockpile, the third one is refused
+    for expected in [true, true, false] {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Carbon,
+            })
+            .unwrap();
+        match rx_expl_local.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => {
+                assert_eq!(resource.is_some(), expected)
+            }
+            _ => panic!("No response was received for GenerateResourceRequest"),
+        }
+    }
+
+    tx_orch_in.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    drop(tx_orch_in);
+    let _ = handle.join();
+}
+
+#[test] // with priors the AI leaves the safe state without waiting for three intervals
+fn test_priors_skip_safe_start() {
+    let planet_id = 46;
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    let ai = CiucAI::with_config(CiucConfig {
+        sunray_prior: Some(Prior {
+            mean_interval_ms: 100.0,
+            pseudo_count: 3,
+        }),
+        asteroid_prior: Some(Prior {
+            mean_interval_ms: 10000.0,
+            pseudo_count: 3,
+        }),
+        ..Default::default()
+    });
+    let seeded = ai.export_state();
+    assert_eq!(seeded.count_sunrays, 3);
+    assert_eq!(seeded.estimate_asteroid_ms, 10000.0);
+    let decisions = ai.decision_log();
+    let mut planet = create_planet_with_ai(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, ai);
+
+    let explorer_id = 2;
+    let (tx_expl_local, rx_expl_local) = crossbeam_channel::unbounded();
+
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    tx_orch_in
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_expl_local,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // 1 sunray for the rocket and 3 charged cells: in safe state they would all be preserved
+    for _ in 0..4 {
+        tx_orch_in
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+    }
+
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource: BasicResourceType::Carbon,
+        })
+        .unwrap();
+    match rx_expl_local.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => {
+            assert!(
+                resource.is_some(),
+                "The prior should allow generating carbon"
+            )
+        }
+        _ => panic!("No response was received for GenerateResourceRequest"),
+    }
+    assert_eq!(decisions.last().unwrap().state, AIState::StatisticState);
+
+    tx_orch_in.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    drop(tx_orch_in);
+    let _ = handle.join();
+}
+
+//-----------------------Persistence-------------------------
+
+#[test] // the learned state is saved on stop and a new AI resumes from it
+fn test_state_saved_and_restored() {
+    let planet_id = 45;
+    let path = std::env::temp_dir().join(format!("ciuc_state_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (_tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    let config = CiucConfig {
+        state_path: Some(path.clone()),
+        ..Default::default()
+    };
+    let mut planet =
+        create_planet_with_config(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, config);
+
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+
+    // 4 sunrays give 3 intervals
+    for _ in 0..4 {
+        thread::sleep(Duration::from_millis(20));
+        tx_orch_in
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+    }
+
+    tx_orch_in.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    match rx_orch_out.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToOrchestrator::StopPlanetAIResult { planet_id: _ }) => {}
+        _ => panic!("StopPlanetAIResult was not received within the timeout period."),
+    }
+    tx_orch_in.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    let _ = handle.join();
+
+    let saved = CiucAIState::load(&path).expect("The state should have been saved on stop");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(saved.count_sunrays, 3);
+    assert_eq!(saved.count_asteroids, 0);
+    assert!(saved.estimate_sunray_ms > 0.0);
+    assert_eq!(saved.config.state_path, Some(path));
+    assert_eq!(
+        CiucAIState::from_bytes(&saved.to_bytes().unwrap()).unwrap(),
+        saved
+    );
+
+    let restored = CiucAI::from_state(saved.clone());
+    assert_eq!(restored.export_state(), saved);
+}
+
+//-----------------------Simulator-------------------------
+
+#[test] // scripted events on the virtual clock, no sleeping
+fn test_simulation_scripted() {
+    let explorer_id = 3;
+    let report = Simulation::new(CiucConfig::default(), 10_000)
+        .event(100, SimEventKind::Sunray) // rocket
+        .event(200, SimEventKind::Sunray)
+        .event(300, SimEventKind::CarbonRequest { explorer_id })
+        .event(400, SimEventKind::Asteroid)
+        .event(500, SimEventKind::Asteroid)
+        .event(550, SimEventKind::Asteroid)
+        .event(600, SimEventKind::Sunray)
+        .run()
+        .unwrap();
+
+    let outcomes: Vec<SimOutcome> = report.timeline.iter().map(|e| e.outcome).collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            SimOutcome::SunrayCharged,
+            SimOutcome::SunrayCharged,
+            SimOutcome::CarbonRefused, // Safe state keeps the only charged cell
+            SimOutcome::AsteroidDeflected, // The cell becomes the new rocket
+            SimOutcome::AsteroidDeflected,
+            SimOutcome::PlanetDestroyed, // No rocket and no charged cell left
+        ]
+    );
+    assert_eq!(report.timeline[1].charged_cells, 1);
+    assert!(report.timeline[1].has_rocket);
+    assert!(!report.stats.survived);
+    assert_eq!(report.stats.destroyed_at_ms, Some(550));
+    assert_eq!(report.stats.carbon_requests, 1);
+    assert_eq!(report.stats.carbon_delivered, 0);
+    assert_eq!(report.metrics.sunrays_received, 2);
+    assert_eq!(report.decisions.len(), 1);
+    // The time in state is virtual: the planet lived 550 ms
+    assert_eq!(
+        report.metrics.time_in_safe_state_ms + report.metrics.time_in_statistic_state_ms,
+        550
+    );
+}
+
+#[test] // the same seed gives the same run
+fn test_simulation_seeded() {
+    let simulation = |seed| {
+        Simulation::new(CiucConfig::default(), 60_000)
+            .with_seed(seed)
+            .stream(RandomStream {
+                kind: SimEventKind::Sunray,
+                interval_ms: 500,
+                jitter_ms: 200,
+            })
+            .stream(RandomStream {
+                kind: SimEventKind::Asteroid,
+                interval_ms: 5_000,
+                jitter_ms: 1_000,
+            })
+            .stream(RandomStream {
+                kind: SimEventKind::CarbonRequest { explorer_id: 1 },
+                interval_ms: 700,
+                jitter_ms: 300,
+            })
+    };
+
+    let first = simulation(7).run().unwrap();
+    let second = simulation(7).run().unwrap();
+    assert_eq!(first.timeline, second.timeline);
+    assert_eq!(first.decisions, second.decisions);
+    assert!(first.stats.survived);
+    assert!(first.stats.carbon_delivered > 0);
+    assert_ne!(simulation(7).schedule(), simulation(8).schedule());
+}
+
+#[test] // QA scenarios are plain text files
+fn test_simulation_scenario_file() {
+    let simulation = Simulation::load_scenario(
+        "tests/scenarios/statistic_state.scenario",
+        CiucConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(simulation.duration_ms, 3_000);
+    assert_eq!(simulation.seed, 1);
+    assert_eq!(simulation.streams.len(), 2);
+
+    let report = simulation.run().unwrap();
+    assert!(report.stats.survived);
+    assert!(report.stats.carbon_delivered > 0);
+    assert_eq!(report.decisions[0].state, AIState::SafeState);
+    assert_eq!(
+        report.decisions.last().unwrap().state,
+        AIState::StatisticState
+    );
+    let cells_requests = report
+        .timeline
+        .iter()
+        .filter(|e| e.at_ms == 2_500 && matches!(e.outcome, SimOutcome::AvailableCells(_)))
+        .count();
+    assert_eq!(cells_requests, 2);
+}
+
+#[test]
+fn test_simulation_scenario_parsing() {
+    let simulation = Simulation::from_scenario(
+        "sunray at 100ms # comment\n\nasteroid at 1.5s\nexplorer 7 requests carbon x3 at 2s\n",
+        CiucConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(simulation.duration_ms, 2_000);
+    assert_eq!(
+        simulation.schedule(),
+        vec![
+            SimEvent {
+                at_ms: 100,
+                kind: SimEventKind::Sunray
+            },
+            SimEvent {
+                at_ms: 1_500,
+                kind: SimEventKind::Asteroid
+            },
+            SimEvent {
+                at_ms: 2_000,
+                kind: SimEventKind::CarbonRequest { explorer_id: 7 }
+            },
+            SimEvent {
+                at_ms: 2_000,
+                kind: SimEventKind::CarbonRequest { explorer_id: 7 }
+            },
+            SimEvent {
+                at_ms: 2_000,
+                kind: SimEventKind::CarbonRequest { explorer_id: 7 }
+            },
+        ]
+    );
+
+    let stream = Simulation::from_scenario(
+        "duration 10s\nasteroid every 2s ±500ms",
+        CiucConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        stream.streams,
+        vec![RandomStream {
+            kind: SimEventKind::Asteroid,
+            interval_ms: 2_000,
+            jitter_ms: 500
+        }]
+    );
+
+    let err =
+        Simulation::from_scenario("sunray at 1s\nmeteor at 2s", CiucConfig::default()).unwrap_err();
+    assert_eq!(err, "Line 2: Unknown event 'meteor at 2s'");
+    assert!(Simulation::from_scenario("sunray every 1s", CiucConfig::default()).is_err());
+
+    // Events that can't happen within the duration are reported, not dropped
+    let err = Simulation::from_scenario(
+        "duration 10s\nsunray at 1s\n\nasteroid x2 at 12s",
+        CiucConfig::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err, "Line 4: the event at 12000ms is after the duration of 10000ms");
+    let err =
+        Simulation::from_scenario("duration 10s\nsunray every 15s ±2s", CiucConfig::default())
+            .unwrap_err();
+    assert!(err.starts_with("Line 2: "), "{}", err);
+    assert!(
+        Simulation::from_scenario("duration 10s\nsunray every 11s ±2s", CiucConfig::default())
+            .is_ok()
+    );
+}
+
+#[test] // a recorded run replays with the same decisions, a different AI diverges
+fn test_trace_record_and_replay() {
+    let path = std::env::temp_dir().join(format!("ciuc_trace_{}.jsonl", std::process::id()));
+    let with_stock = CiucConfig {
+        stockpile_capacity: 2,
+        prefill_stockpile: true,
+        ..Default::default()
+    };
+    let config = CiucConfig {
+        trace_path: Some(path.clone()),
+        ..with_stock.clone()
+    };
+    let recorded = Simulation::load_scenario("tests/scenarios/statistic_state.scenario", config)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let trace = read_trace(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(trace.len(), recorded.timeline.len());
+    assert_eq!(trace[0].event, recorded.timeline[0].kind);
+    assert_eq!(trace[0].outcome, recorded.timeline[0].outcome);
+
+    let same = replay(&trace, with_stock).unwrap();
+    assert_eq!(same.divergences, vec![]);
+    assert_eq!(same.simulation.decisions, recorded.decisions);
+
+    let different = replay(&trace, CiucConfig::default()).unwrap();
+    let first = different.first_divergence().unwrap();
+    assert_eq!(first.recorded, trace[first.index].outcome);
+    assert_ne!(first.replayed, Some(first.recorded));
+}
+
+#[test] // every policy is simulated in every environment of the grid
+fn test_benchmark_report() {
+    let grid = BenchGrid {
+        sunray_interval_ms: vec![200, 1_000],
+        asteroid_interval_ms: vec![3_000],
+        request_interval_ms: vec![300],
+        jitter: 0.2,
+        seeds: 2,
+        duration_ms: 10_000,
+    };
+    let policies = BenchPolicy::statistic_vs_safe();
+    let report = run_benchmark(&policies, &grid).unwrap();
+
+    assert_eq!(report.rows.len(), 4);
+    assert_eq!(report.rows[0].policy, "statistic");
+    assert_eq!(report.rows[1].policy, "safe");
+    for row in &report.rows {
+        assert_eq!(row.runs, 2);
+        assert!((0.0..=1.0).contains(&row.survival_rate));
+        assert!(row.carbon_delivered <= row.carbon_requests);
+    }
+    // Same seeds, same results
+    assert_eq!(run_benchmark(&policies, &grid).unwrap(), report);
+
+    let csv = report.to_csv();
+    assert_eq!(csv.lines().count(), 5);
+    assert!(csv.starts_with("policy,sunray_interval_ms,"));
+    assert!(
+        csv.lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("statistic,200,3000,300,2,")
+    );
+    assert_eq!(report.to_table().lines().count(), 5);
+
+    // A name with a separator or a quote is quoted, with the quotes doubled
+    let mut named = report.clone();
+    named.rows.truncate(1);
+    named.rows[0].policy = "margin 0.2, \"tight\"".to_string();
+    assert!(
+        named
+            .to_csv()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("\"margin 0.2, \"\"tight\"\"\",200,3000,300,2,")
+    );
+}
+
+#[test] // the tuner never recommends something worse than the starting configuration
+fn test_tuner_recommends_config() {
+    let environments = BenchGrid {
+        sunray_interval_ms: vec![300],
+        asteroid_interval_ms: vec![3_000],
+        request_interval_ms: vec![200],
+        jitter: 0.2,
+        seeds: 2,
+        duration_ms: 10_000,
+    };
+    let mut tuner = Tuner::new(
+        CiucConfig::default(),
+        environments,
+        SearchMethod::Random { samples: 3 },
+    );
+    tuner.seed = 5;
+    let result = tuner.run().unwrap();
+
+    assert_eq!(result.evaluated, 4);
+    assert!(result.evaluation.score >= result.baseline.score);
+    assert!(result.evaluation.survival_rate >= tuner.objective.min_survival_rate);
+    assert_eq!(tuner.evaluate(&result.config).unwrap(), result.evaluation);
+    let config: CiucConfig = serde_json::from_str(&result.config_json().unwrap()).unwrap();
+    assert_eq!(config, result.config);
+
+    // Below the survival rate, the configuration closer to it wins whatever the carbon delivered
+    let objective = Objective {
+        waste_weight: 1.0,
+        ..Default::default()
+    };
+    let worse = objective.score(0.5, 1_000.0, 0.0);
+    let better = objective.score(0.6, 0.0, 0.0);
+    let closer = objective.score(0.98, 0.0, 0.0);
+    assert!(worse < better && better < closer);
+    assert!(closer < objective.score(0.99, 0.0, 10_000.0));
+}
+
+#[test] // a near-death tightens the margin, a long safe period relaxes it
+fn test_adaptive_margin() {
+    let planet_id = 47;
+    let _ = captured_logs(planet_id); // Install the logger
+    let config = CiucConfig {
+        adaptive_margin: Some(AdaptiveMarginConfig {
+            min_margin: -1,
+            max_margin: 2,
+            relax_after_ms: 1_000,
+        }),
+        ..Default::default()
+    };
+    let explorer_id = 1;
+    let mut simulation = Simulation::new(config, 3_000)
+        .event(100, SimEventKind::Sunray) // rocket
+        .event(200, SimEventKind::Asteroid) // deflected, nothing left: near-death
+        .event(300, SimEventKind::Sunray) // rocket
+        .event(400, SimEventKind::Sunray)
+        .event(500, SimEventKind::Sunray)
+        .event(600, SimEventKind::Sunray)
+        .event(700, SimEventKind::Sunray)
+        .event(750, SimEventKind::CarbonRequest { explorer_id })
+        .event(2_000, SimEventKind::Sunray) // long safe period
+        .event(2_100, SimEventKind::CarbonRequest { explorer_id });
+    simulation.planet_id = planet_id;
+    let report = simulation.run().unwrap();
+
+    let tightened = &report.decisions[0];
+    assert_eq!(tightened.margin, 1);
+    assert_eq!(tightened.safe_cells, 4);
+    assert_eq!(tightened.charged_cells, 4);
+    assert!(!tightened.granted);
+    let relaxed = &report.decisions[1];
+    assert_eq!(relaxed.margin, 0);
+    assert!(relaxed.granted);
+
+    let adjustments: Vec<String> = captured_logs(planet_id)
+        .into_iter()
+        .filter(|(_, line)| line.contains("Changed the safe-cell margin"))
+        .map(|(_, line)| line)
+        .collect();
+    assert_eq!(adjustments.len(), 2);
+    assert!(adjustments[0].contains("from 0 to 1 after a near-death"));
+    assert!(adjustments[1].contains("from 1 to 0 after a long safe period"));
+}
+
 //-----------------------Other test-------------------------
 #[test] // check if the ema function returns the correct result
 fn test_ema() {
@@ -850,3 +1761,849 @@ fn test_ema() {
     assert_eq!(result, 13.0);
     assert_eq!(update_ema(result, 10.0, 0.3), 12.1);
 }
+
+#[test] // the Q-learning policy learns in the simulator and is loaded at planet creation
+fn test_q_learning_policy() {
+    let environments = BenchGrid {
+        sunray_interval_ms: vec![300],
+        asteroid_interval_ms: vec![3_000],
+        request_interval_ms: vec![200],
+        jitter: 0.2,
+        seeds: 1,
+        duration_ms: 20_000,
+    };
+    let training = QLearningConfig {
+        exploration: 0.3,
+        ..Default::default()
+    };
+    // Untrained: every action is worth the same, the safest one is chosen
+    let observation = Observation {
+        state: AIState::SafeState,
+        charged_cells: 1,
+        cell_count: 5,
+        has_rocket: true,
+        explorers: 1,
+        since_last_sunray_ms: Some(0),
+        since_last_asteroid_ms: None,
+        until_next_sunray_ms: None,
+        until_next_asteroid_ms: None,
+        estimate_sunray_ms: 0.0,
+        estimate_asteroid_ms: 0.0,
+        count_sunrays: 0,
+        count_asteroids: 0,
+    };
+    let mut untrained = QLearningPolicy::new(QLearningConfig {
+        exploration: 0.0,
+        ..Default::default()
+    });
+    assert_eq!(untrained.safe_cells(&observation), 5);
+    assert_eq!(untrained.decide(&observation), 5);
+
+    let table =
+        train_q_learning(&CiucConfig::default(), &training, &environments, 30).unwrap();
+    assert!(!table.is_empty());
+
+    // Deployed: no exploration, no learning
+    let config = CiucConfig {
+        q_learning: Some(QLearningConfig {
+            learning_rate: 0.0,
+            exploration: 0.0,
+            table: table.clone(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let report = environments
+        .simulation(&BenchPolicy::new("q_learning", config.clone()), 300, 3_000, 200, 99)
+        .run()
+        .unwrap();
+    assert!(report.stats.survived);
+    assert!(report.stats.carbon_delivered > 0);
+    assert_eq!(report.q_table, Some(table.clone()));
+
+    // The table survives a round trip through the saved state
+    let saved = CiucAI::with_config(config).export_state();
+    assert_eq!(saved.config.q_learning.clone().unwrap().table, table);
+
+    // A table of another size is refused when loaded
+    let mut json: serde_json::Value = serde_json::from_slice(&saved.to_bytes().unwrap()).unwrap();
+    json["config"]["q_learning"]["table"]["values"] = serde_json::json!([0.0]);
+    let err = CiucAIState::from_bytes(json.to_string().as_bytes()).unwrap_err();
+    assert!(err.contains("The Q-table has 1 values"), "{}", err);
+}
+
+// strategy giving away every charged cell, the rest as the default strategy
+struct GreedyStrategy;
+
+impl CiucStrategy for GreedyStrategy {
+    fn safe_cells(&self, observation: &Observation, _config: &CiucConfig) -> SafeCells {
+        SafeCells::new(0, observation.cell_count)
+    }
+
+    fn build_rocket(&self, observation: &Observation, trigger: RocketTrigger) -> bool {
+        DefaultStrategy.build_rocket(observation, trigger)
+    }
+
+    fn next_state(&self, observation: &Observation, config: &CiucConfig) -> AIState {
+        DefaultStrategy.next_state(observation, config)
+    }
+}
+
+#[test] // a custom strategy replaces the decisions, the handlers stay the same
+fn test_custom_strategy() {
+    let planet_id = 48;
+    let (tx_orch_in, rx_orch_in) = crossbeam_channel::unbounded::<OrchestratorToPlanet>();
+    let (tx_orch_out, rx_orch_out) = crossbeam_channel::unbounded::<PlanetToOrchestrator>();
+    let (tx_expl, rx_expl_in) = crossbeam_channel::unbounded::<ExplorerToPlanet>();
+    let ai = CiucAI::new().with_strategy(Box::new(GreedyStrategy));
+    let decisions = ai.decision_log();
+    let mut planet = create_planet_with_ai(rx_orch_in, tx_orch_out, rx_expl_in, planet_id, ai);
+
+    let explorer_id = 2;
+    let (tx_expl_local, rx_expl_local) = crossbeam_channel::unbounded();
+    let handle = thread::spawn(move || {
+        let _ = planet.run();
+    });
+
+    tx_orch_in
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    tx_orch_in
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_expl_local,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // two sunrays: one for the rocket and one charged cell, refused by the default strategy
+    for _ in 0..2 {
+        tx_orch_in
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch_out.recv_timeout(Duration::from_millis(200));
+    }
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource: BasicResourceType::Carbon,
+        })
+        .unwrap();
+    match rx_expl_local.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::GenerateResourceResponse { resource: Some(_) }) => {}
+        _ => panic!("The greedy strategy should give the carbon"),
+    }
+    let decision = decisions.last().unwrap();
+    assert_eq!(decision.safe_cells, 0);
+    assert!(decision.granted);
+
+    // the default strategy still builds the rocket
+    while rx_orch_out.try_recv().is_ok() {} // acks of the previous messages
+    tx_orch_in
+        .send(OrchestratorToPlanet::Asteroid(Asteroid::default()))
+        .unwrap();
+    match rx_orch_out.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToOrchestrator::AsteroidAck { rocket, .. }) => assert!(rocket.is_some()),
+        _ => panic!("The asteroid should have been deflected"),
+    }
+
+    drop(tx_orch_in);
+    drop(tx_expl);
+    let _ = handle.join();
+}
+
+// policy giving away every cell, but exploring more cells than the planet has, recording what it is asked
+struct RecordingPolicy {
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl Policy for RecordingPolicy {
+    fn safe_cells(&self, _observation: &Observation) -> u32 {
+        0
+    }
+
+    fn decide(&mut self, _observation: &Observation) -> u32 {
+        self.calls.lock().unwrap().push("decide".to_string());
+        100
+    }
+
+    fn feedback(&mut self, feedback: PolicyFeedback) {
+        self.calls.lock().unwrap().push(format!("{:?}", feedback));
+    }
+}
+
+#[test] // the policy is asked once per request, bounded like the thresholds and not told about the stock
+fn test_policy_decision() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let ai = CiucAI::with_config(CiucConfig {
+        safe_cells: SafeCellsConfig {
+            safe_cells: 0, // the floor of the policy in 'SafeState'
+            ..Default::default()
+        },
+        stockpile_capacity: 1,
+        prefill_stockpile: true,
+        ..Default::default()
+    })
+    .with_policy(Box::new(RecordingPolicy {
+        calls: calls.clone(),
+    }));
+    let decisions = ai.decision_log();
+    let orchestrator = TestOrchestrator::started(61, ai)
+        .unwrap()
+        .with_timeout(Duration::from_millis(200));
+    let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+    orchestrator.send_sunray().unwrap(); // rocket
+    orchestrator.send_sunray().unwrap(); // charged cell, turned into stocked carbon
+
+    // from the stock: the policy decides nothing
+    assert!(explorer.request_carbon().unwrap().is_some());
+    assert!(decisions.last().unwrap().from_stock);
+    assert!(calls.lock().unwrap().is_empty());
+
+    assert!(explorer.request_carbon().unwrap().is_none());
+    assert_eq!(*calls.lock().unwrap(), ["decide", "CarbonRefused"]);
+    let decision = decisions.last().unwrap();
+    assert_eq!(decision.safe_cells, 5); // every cell of the planet
+    assert_eq!(decision.adjustments[0].requested, 100);
+    assert_eq!(decision.adjustments[0].value, 5);
+}
+
+#[test] // the test orchestrator drives the planet and checks every answer
+fn test_orchestrator_helpers() {
+    let orchestrator = TestOrchestrator::started(49, CiucAI::new())
+        .unwrap()
+        .with_timeout(Duration::from_millis(200));
+
+    orchestrator.send_sunray().unwrap(); // rocket
+    orchestrator.send_sunray().unwrap(); // charged cell
+    let state = orchestrator.request_state().unwrap();
+    assert!(state.has_rocket);
+    assert_eq!(state.charged_cells_count, 1);
+
+    assert!(orchestrator.send_asteroid().unwrap().is_some());
+    assert!(orchestrator.request_state().unwrap().has_rocket); // rebuilt with the charged cell
+
+    // a stopped planet only answers 'Stopped'
+    orchestrator.stop_ai().unwrap();
+    let err = orchestrator.send_sunray().unwrap_err();
+    assert!(err.contains("Stopped"), "{err}");
+    orchestrator.start_ai().unwrap();
+
+    assert!(orchestrator.send_asteroid().unwrap().is_some());
+    assert!(orchestrator.send_asteroid().unwrap().is_none()); // destroyed
+    orchestrator.assert_no_pending().unwrap();
+}
+
+#[test] // explorer requests and the planet's answers, end to end
+fn test_mock_explorer() {
+    let orchestrator = TestOrchestrator::started(50, CiucAI::new())
+        .unwrap()
+        .with_timeout(Duration::from_millis(200));
+    let explorer = MockExplorer::arrive(&orchestrator, 3).unwrap();
+
+    let resources = explorer.request_supported_resources().unwrap();
+    assert_eq!(resources.len(), 1);
+    assert!(resources.contains(&BasicResourceType::Carbon));
+    assert!(explorer.request_supported_combinations().unwrap().is_empty());
+
+    // rocket and one charged cell: nothing above the safe cells
+    orchestrator.send_sunray().unwrap();
+    orchestrator.send_sunray().unwrap();
+    assert_eq!(explorer.request_available_cells().unwrap(), 0);
+    assert!(explorer.request_carbon().unwrap().is_none());
+    explorer.assert_no_pending().unwrap();
+
+    // a stopped planet answers 'Stopped' to the explorers too
+    orchestrator.stop_ai().unwrap();
+    let err = explorer.request_carbon().unwrap_err();
+    assert!(err.contains("Stopped"), "{err}");
+    orchestrator.start_ai().unwrap();
+
+    let explorer_id = explorer.explorer_id();
+    explorer.leave(&orchestrator).unwrap();
+    // a new explorer with the same id is answered on its own channel
+    let explorer = MockExplorer::arrive(&orchestrator, explorer_id).unwrap();
+    assert_eq!(explorer.request_available_cells().unwrap(), 0);
+    orchestrator.kill().unwrap();
+}
+
+// ---------------- Property tests ----------------
+// Each case is a random sequence of events on the virtual clock, built from its seed:
+// a failing case prints the seed and the events, to be replayed as a scripted simulation
+
+const PROPERTY_CASES: u64 = 200;
+
+// random sequence of sunrays, asteroids and explorer requests with random gaps
+fn random_simulation(seed: u64, config: CiucConfig) -> Simulation {
+    let mut rng = SimRng::new(seed);
+    let mut simulation = Simulation::new(config, 0).with_seed(seed);
+    let mut at = 0;
+    for _ in 0..rng.range_i64(10, 80) {
+        at += rng.range_i64(1, 2_000) as u64;
+        let explorer_id = rng.range_i64(1, 3) as u32;
+        let kind = match rng.range_i64(0, 9) {
+            0..=3 => SimEventKind::Sunray,
+            4 => SimEventKind::Asteroid,
+            5..=6 => SimEventKind::CarbonRequest { explorer_id },
+            7..=8 => {
+                // sometimes ask for the advertised cells right away
+                if rng.range_i64(0, 1) == 1 {
+                    simulation = simulation
+                        .event(at, SimEventKind::AvailableCellsRequest { explorer_id });
+                    SimEventKind::CarbonRequest { explorer_id }
+                } else {
+                    SimEventKind::AvailableCellsRequest { explorer_id }
+                }
+            }
+            _ => SimEventKind::OtherExplorerRequest { explorer_id },
+        };
+        simulation = simulation.event(at, kind);
+    }
+    simulation.duration_ms = at;
+    simulation
+}
+
+// random thresholds and stockpile settings, every decision kept
+fn random_config(rng: &mut SimRng) -> CiucConfig {
+    let cells = |rng: &mut SimRng| rng.range_i64(0, 5) as u32;
+    CiucConfig {
+        safe_cells: SafeCellsConfig {
+            safe_cells: cells(rng),
+            safe_cells_far_asteroid: cells(rng),
+            safe_cells_near_asteroid: cells(rng),
+            sunray_imminent_threshold: rng.next_f64() * 1.5,
+            asteroid_far_threshold: rng.next_f64() * 1.5,
+            cells_per_extra_explorer: rng.range_i64(-1, 1) as i32,
+        },
+        avoid_sunray_waste: rng.range_i64(0, 1) == 1,
+        stockpile_capacity: rng.range_i64(0, 3) as usize,
+        prefill_stockpile: rng.range_i64(0, 1) == 1,
+        decision_history: 10_000,
+        outliers: (rng.range_i64(0, 1) == 1).then(OutlierConfig::default),
+        regime_change: (rng.range_i64(0, 1) == 1).then(RegimeChangeConfig::default),
+        periodicity: (rng.range_i64(0, 1) == 1).then(PeriodicityConfig::default),
+        ..Default::default()
+    }
+}
+
+// runs every case, returns the failing seed with its events
+fn check_property(
+    config: impl Fn(&mut SimRng) -> CiucConfig,
+    property: impl Fn(&CiucConfig, &SimReport) -> Result<(), String>,
+) {
+    for seed in 0..PROPERTY_CASES {
+        let simulation = random_simulation(seed, config(&mut SimRng::new(!seed)));
+        let report = simulation.run().unwrap();
+        if let Err(e) = property(&simulation.config, &report) {
+            panic!(
+                "seed {seed}: {e}\nconfig: {:?}\nevents: {:?}",
+                simulation.config,
+                simulation.schedule()
+            );
+        }
+    }
+}
+
+#[test] // carbon is made from a cell only when the charged cells are above the configured safe cells
+fn property_no_carbon_at_or_below_safe_target() {
+    check_property(random_config, no_carbon_at_or_below_safe_target);
+}
+
+#[test] // the advertised cells (charged above the safe target, plus the stocked carbon) can be obtained right away
+fn property_available_cells_not_overstated() {
+    check_property(random_config, |_, report| {
+        available_cells_not_overstated(report)
+    });
+}
+
+#[test] // in 'SafeState' the explorers never take the planet below the safe cells, even with an exploring policy
+fn property_safe_state_keeps_safe_cells() {
+    check_property(
+        |rng| CiucConfig {
+            statistic_state: rng.range_i64(0, 1) == 1,
+            q_learning: (rng.range_i64(0, 1) == 1).then(|| QLearningConfig {
+                exploration: 0.5,
+                ..Default::default()
+            }),
+            ..random_config(rng)
+        },
+        safe_state_keeps_safe_cells,
+    );
+}
+
+#[test] // random bytes through the fuzz entry point: no panic, no broken invariant
+fn test_fuzz_entry_point() {
+    assert!(fuzz_events(&[]).is_ok());
+    for seed in 0..100 {
+        let mut rng = SimRng::new(seed);
+        let data: Vec<u8> = (0..rng.range_i64(0, 200))
+            .map(|_| rng.next_u64() as u8)
+            .collect();
+        if let Err(e) = fuzz_events(&data) {
+            panic!("seed {seed}: {e}\nsimulation: {:?}", decode_simulation(&data));
+        }
+    }
+}
+
+#[test] // every stage of the safe cells is bounded by the cells of the planet and recorded
+fn test_safe_cells_pipeline() {
+    let run = |safe_cells: SafeCellsConfig| {
+        let config = CiucConfig {
+            safe_cells,
+            // estimates already usable: 'StatisticState' after the first sunray
+            sunray_prior: Some(Prior {
+                mean_interval_ms: 100.0,
+                pseudo_count: 3,
+            }),
+            asteroid_prior: Some(Prior {
+                mean_interval_ms: 10_000.0,
+                pseudo_count: 3,
+            }),
+            stockpile_capacity: 0,
+            ..Default::default()
+        };
+        let report = Simulation::new(config, 1_000)
+            .event(100, SimEventKind::Sunray) // rocket
+            .event(200, SimEventKind::Sunray)
+            .event(300, SimEventKind::Sunray)
+            .event(400, SimEventKind::ExplorerArrival { explorer_id: 1 })
+            .event(400, SimEventKind::ExplorerArrival { explorer_id: 2 })
+            .event(1_000, SimEventKind::CarbonRequest { explorer_id: 1 }) // sunray imminent
+            .run()
+            .unwrap();
+        report.decisions[0].clone()
+    };
+
+    // nothing left to release for the sunray, the explorers add two cells
+    let decision = run(SafeCellsConfig {
+        safe_cells_far_asteroid: 0,
+        safe_cells_near_asteroid: 0,
+        cells_per_extra_explorer: 2,
+        ..Default::default()
+    });
+    assert_eq!(decision.state, AIState::StatisticState);
+    let stages: Vec<_> = decision
+        .adjustments
+        .iter()
+        .map(|a| (a.stage, a.requested, a.applied, a.value))
+        .collect();
+    assert_eq!(
+        stages,
+        vec![
+            (SafeCellsStage::Base, 0, 0, 0),
+            (SafeCellsStage::SunrayImminent, -1, 0, 0),
+            (SafeCellsStage::ExplorerDemand, 2, 2, 2),
+        ]
+    );
+    assert_eq!(decision.safe_cells, 2);
+    assert_eq!(decision.sunray_adjustment, 0);
+    assert!(!decision.granted);
+
+    // thresholds above the cells of the planet are capped
+    let decision = run(SafeCellsConfig {
+        safe_cells_far_asteroid: 9,
+        safe_cells_near_asteroid: 9,
+        ..Default::default()
+    });
+    assert_eq!(decision.adjustments[0].requested, 9);
+    assert_eq!(decision.safe_cells, 4); // 5 cells, one released for the sunray
+}
+
+#[test] // a clock going backwards or jumping ahead doesn't corrupt the estimates
+fn test_clock_skew() {
+    let clock = Clock::manual(1_000_000);
+    let ai = CiucAI::with_clock(CiucConfig::default(), clock.clone());
+    let metrics = ai.metrics();
+    let decisions = ai.decision_log();
+    let orchestrator = TestOrchestrator::started(51, ai).unwrap();
+    let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+
+    orchestrator.send_sunray().unwrap(); // first sunray, no interval yet
+    explorer.request_carbon().unwrap();
+    let decision = decisions.last().unwrap();
+    assert_eq!(decision.since_last_sunray_ms, Some(0));
+    assert_eq!(decision.since_last_asteroid_ms, None);
+
+    clock.set(900_000); // backwards
+    orchestrator.send_sunray().unwrap();
+    assert_eq!(metrics.snapshot().interval_samples_discarded, 1);
+    explorer.request_carbon().unwrap();
+    assert_eq!(decisions.last().unwrap().estimate_sunray_ms, 0.0);
+
+    clock.advance(24 * 3_600_000); // a day later
+    orchestrator.send_sunray().unwrap();
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.interval_samples_clamped, 1);
+    assert_eq!(snapshot.sunray_interval_ms.count, 1);
+    explorer.request_carbon().unwrap();
+    let max_interval_ms = CiucConfig::default().max_interval_ms as f64;
+    assert_eq!(decisions.last().unwrap().estimate_sunray_ms, max_interval_ms);
+}
+
+#[test] // a single pause of the orchestrator doesn't reach the estimates
+fn test_outlier_intervals() {
+    let run = |action: OutlierAction| {
+        let clock = Clock::manual(1_000_000);
+        let config = CiucConfig {
+            outliers: Some(OutlierConfig {
+                action,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let ai = CiucAI::with_clock(config, clock.clone());
+        let metrics = ai.metrics();
+        let decisions = ai.decision_log();
+        let orchestrator = TestOrchestrator::started(52, ai).unwrap();
+        let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+
+        for i in 0..8 {
+            orchestrator.send_sunray().unwrap();
+            clock.advance(if i % 2 == 0 { 950 } else { 1050 });
+        }
+        clock.advance(120_000); // the orchestrator was stopped
+        orchestrator.send_sunray().unwrap();
+        explorer.request_carbon().unwrap();
+        (metrics.snapshot(), decisions.last().unwrap().estimate_sunray_ms)
+    };
+
+    let (metrics, estimate) = run(OutlierAction::Reject);
+    assert_eq!(metrics.interval_samples_rejected, 1);
+    assert_eq!(metrics.interval_samples_replaced, 0);
+    assert!((900.0..1100.0).contains(&estimate), "estimate {estimate}");
+
+    let (metrics, estimate) = run(OutlierAction::ReplaceWithMedian);
+    assert_eq!(metrics.interval_samples_rejected, 0);
+    assert_eq!(metrics.interval_samples_replaced, 1);
+    assert!((900.0..1100.0).contains(&estimate), "estimate {estimate}");
+    assert!(to_prometheus(&metrics, 52).contains("ciuc_interval_samples_replaced_total"));
+}
+
+#[test] // the time in which the AI is stopped doesn't count in the intervals
+fn test_pause_aware_timers() {
+    let run = |pause_reset_ms: Option<i64>| {
+        let clock = Clock::manual(1_000_000);
+        let prior = |mean_interval_ms| {
+            Some(Prior {
+                mean_interval_ms,
+                pseudo_count: 3,
+            })
+        };
+        let config = CiucConfig {
+            sunray_prior: prior(1_000.0),
+            asteroid_prior: prior(10_000.0),
+            pause_reset_ms,
+            ..Default::default()
+        };
+        let ai = CiucAI::with_clock(config, clock.clone());
+        let metrics = ai.metrics();
+        let decisions = ai.decision_log();
+        let orchestrator = TestOrchestrator::started(53, ai).unwrap();
+        let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+
+        orchestrator.send_sunray().unwrap();
+        explorer.request_carbon().unwrap();
+        assert_eq!(decisions.last().unwrap().state, AIState::StatisticState);
+
+        clock.advance(400);
+        orchestrator.stop_ai().unwrap();
+        clock.advance(60_000);
+        orchestrator.start_ai().unwrap();
+        clock.advance(600);
+        orchestrator.send_sunray().unwrap();
+        explorer.request_carbon().unwrap();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.time_paused_ms, 60_000);
+        // only the running time is spent in a state
+        assert_eq!(
+            snapshot.time_in_safe_state_ms + snapshot.time_in_statistic_state_ms,
+            1_000
+        );
+        decisions.last().unwrap()
+    };
+
+    // the interval across the pause is 1s of running time, the estimate doesn't move
+    let decision = run(None);
+    assert_eq!(decision.estimate_sunray_ms, 1_000.0);
+    assert_eq!(decision.since_last_sunray_ms, Some(0));
+    assert_eq!(decision.state, AIState::StatisticState);
+
+    // after a long pause the AI is safe until it has observed the galaxy again
+    let decision = run(Some(30_000));
+    assert_eq!(decision.state, AIState::SafeState);
+    assert_eq!(decision.estimate_sunray_ms, 1_000.0);
+}
+
+#[test] // an escalation of the sunray rate restarts its estimate in safe state
+fn test_regime_change() {
+    let clock = Clock::manual(1_000_000);
+    let prior = |mean_interval_ms| {
+        Some(Prior {
+            mean_interval_ms,
+            pseudo_count: 3,
+        })
+    };
+    let config = CiucConfig {
+        sunray_prior: prior(1_000.0),
+        asteroid_prior: prior(10_000.0),
+        regime_change: Some(RegimeChangeConfig::default()),
+        ..Default::default()
+    };
+    let ai = CiucAI::with_clock(config, clock.clone());
+    let metrics = ai.metrics();
+    let decisions = ai.decision_log();
+    let orchestrator = TestOrchestrator::started(54, ai).unwrap();
+    let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+
+    // a jittered schedule is a single regime
+    for i in 0..12 {
+        clock.advance([900, 1_100, 1_000][i % 3]);
+        orchestrator.send_sunray().unwrap();
+    }
+    explorer.request_carbon().unwrap();
+    assert_eq!(decisions.last().unwrap().state, AIState::StatisticState);
+    assert_eq!(metrics.snapshot().regime_changes, 0);
+
+    let mut sunrays = 0;
+    while metrics.snapshot().regime_changes == 0 {
+        assert!(sunrays < 8, "the change wasn't detected");
+        clock.advance(250);
+        orchestrator.send_sunray().unwrap();
+        sunrays += 1;
+    }
+    explorer.request_carbon().unwrap();
+    let decision = decisions.last().unwrap();
+    assert_eq!(decision.state, AIState::SafeState);
+    assert_eq!(decision.estimate_sunray_ms, 250.0);
+}
+
+#[test] // the sunrays of a fixed timer are predicted from the last one
+fn test_periodic_sunrays() {
+    let run = |intervals: &[i64]| {
+        let clock = Clock::manual(1_000_000);
+        let prior = |mean_interval_ms| {
+            Some(Prior {
+                mean_interval_ms,
+                pseudo_count: 3,
+            })
+        };
+        let config = CiucConfig {
+            sunray_prior: prior(10_000.0),
+            asteroid_prior: prior(100_000.0),
+            periodicity: Some(PeriodicityConfig::default()),
+            ..Default::default()
+        };
+        let ai = CiucAI::with_clock(config, clock.clone());
+        let decisions = ai.decision_log();
+        let orchestrator = TestOrchestrator::started(55, ai).unwrap();
+        let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+
+        orchestrator.send_sunray().unwrap();
+        for interval in intervals {
+            clock.advance(*interval);
+            orchestrator.send_sunray().unwrap();
+        }
+        // 80% of the interval: early for a fixed timer, late enough for the mean interval
+        clock.advance(8_000);
+        explorer.request_carbon().unwrap();
+        let early = decisions.last().unwrap();
+        clock.advance(1_900);
+        explorer.request_carbon().unwrap();
+        (early, decisions.last().unwrap())
+    };
+
+    let (early, imminent) = run(&[10_000; 6]);
+    assert_eq!(early.state, AIState::StatisticState);
+    assert_eq!(early.until_next_sunray_ms, Some(2_000));
+    assert_eq!(early.sunray_adjustment, 0);
+    assert_eq!(imminent.until_next_sunray_ms, Some(100));
+    assert_eq!(imminent.sunray_adjustment, 1);
+
+    // an irregular schedule falls back to the mean interval
+    let (early, _) = run(&[6_000, 14_000, 8_000, 12_000, 7_000, 13_000]);
+    assert_eq!(early.until_next_sunray_ms, None);
+    assert_eq!(early.sunray_adjustment, 1);
+}
+
+#[test] // with 'sample_every' only one event out of n is logged, with the count of the dropped ones
+fn test_log_sample_every() {
+    let planet_id = 56;
+    let _ = captured_logs(planet_id);
+    let mut config = CiucConfig::default();
+    config.logging = config.logging.with_category(
+        LogCategory::Esteem,
+        CategoryLogConfig {
+            sample_every: 3,
+            ..Default::default()
+        },
+    );
+    let ai = CiucAI::with_clock(config, Clock::manual(1_000_000));
+    let orchestrator = TestOrchestrator::started(planet_id, ai).unwrap();
+    for _ in 0..7 {
+        orchestrator.send_sunray().unwrap();
+    }
+
+    let esteem: Vec<String> = captured_logs(planet_id)
+        .into_iter()
+        .map(|(_, line)| line)
+        .filter(|line| line.contains("Updated sunray esteem"))
+        .collect();
+    assert_eq!(esteem.len(), 3, "{:?}", esteem); // the 1st, the 4th and the 7th
+    assert!(!esteem[0].contains("suppressed"));
+    assert!(esteem[1].contains("\"suppressed\": \"2\""), "{}", esteem[1]);
+}
+
+#[test] // with 'max_per_second' the events beyond the limit are dropped until the next second
+fn test_log_max_per_second() {
+    let planet_id = 57;
+    let _ = captured_logs(planet_id);
+    let clock = Clock::manual(1_000_000);
+    let mut config = CiucConfig::default();
+    config.logging = config.logging.with_category(
+        LogCategory::Esteem,
+        CategoryLogConfig {
+            max_per_second: Some(2),
+            ..Default::default()
+        },
+    );
+    let ai = CiucAI::with_clock(config, clock.clone());
+    let orchestrator = TestOrchestrator::started(planet_id, ai).unwrap();
+    let esteem_logs = || {
+        captured_logs(planet_id)
+            .into_iter()
+            .map(|(_, line)| line)
+            .filter(|line| line.contains("Updated sunray esteem"))
+            .collect::<Vec<_>>()
+    };
+
+    for _ in 0..5 {
+        orchestrator.send_sunray().unwrap();
+    }
+    assert_eq!(esteem_logs().len(), 2);
+
+    clock.advance(1_000);
+    orchestrator.send_sunray().unwrap();
+    let esteem = esteem_logs();
+    assert_eq!(esteem.len(), 3);
+    assert!(esteem[2].contains("\"suppressed\": \"3\""), "{}", esteem[2]);
+}
+
+#[test] // the trace of a carbon request is logged even with the history disabled
+fn test_decision_trace_without_history() {
+    let planet_id = 58;
+    let _ = captured_logs(planet_id);
+    let config = CiucConfig {
+        decision_history: 0,
+        ..Default::default()
+    };
+    let ai = CiucAI::with_clock(config, Clock::manual(1_000_000));
+    let decisions = ai.decision_log();
+    let orchestrator = TestOrchestrator::started(planet_id, ai).unwrap();
+    let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+    assert!(explorer.request_carbon().unwrap().is_none()); // no charged cell
+    assert!(decisions.all().is_empty());
+
+    let logs = captured_logs(planet_id);
+    let refusal = logs
+        .iter()
+        .map(|(_, line)| line)
+        .find(|line| line.contains("\"granted\": \"false\""))
+        .expect("the refusal should be logged with its trace");
+    assert!(refusal.contains("\"refusal\": \"Didn't find any charged cell\""), "{}", refusal);
+}
+
+#[test] // without a stockpile the cells can't be freed: a warning at the start, no error for each sunray
+fn test_sunray_waste_without_stockpile() {
+    let planet_id = 62;
+    let _ = captured_logs(planet_id); // Install the logger
+    let config = CiucConfig {
+        avoid_sunray_waste: true,
+        ..Default::default()
+    };
+    assert_eq!(config.warnings().len(), 1);
+    let ai = CiucAI::with_clock(config, Clock::manual(1_000_000));
+    let orchestrator = TestOrchestrator::started(planet_id, ai).unwrap();
+    for _ in 0..8 {
+        orchestrator.send_sunray().unwrap();
+    }
+
+    let logs = captured_logs(planet_id);
+    let warnings: Vec<_> = logs
+        .iter()
+        .filter(|(level, _)| *level == log::Level::Warn)
+        .collect();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].1.contains("stockpile_capacity"));
+    assert!(logs.iter().all(|(level, _)| *level != log::Level::Error));
+}
+
+#[test] // the cells are freed before a predicted sunray by the orchestrator events, never by an explorer query
+fn test_sunray_waste_predicted() {
+    let run = |periodicity: Option<PeriodicityConfig>| {
+        let clock = Clock::manual(1_000_000);
+        let config = CiucConfig {
+            avoid_sunray_waste: true,
+            prefill_stockpile: false,
+            stockpile_capacity: 2,
+            periodicity,
+            ..Default::default()
+        };
+        let ai = CiucAI::with_clock(config, clock.clone());
+        let metrics = ai.metrics();
+        let orchestrator = TestOrchestrator::started(59, ai).unwrap();
+        let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+
+        // 1 sunray for the rocket, 5 for the cells
+        for _ in 0..6 {
+            clock.advance(1_000);
+            orchestrator.send_sunray().unwrap();
+        }
+        let after_sunrays = metrics.snapshot().sunrays_waste_avoided;
+
+        // the next sunray is imminent, but a query doesn't touch the cells
+        clock.advance(900);
+        explorer.request_available_cells().unwrap();
+        let after_query = metrics.snapshot().sunrays_waste_avoided;
+        (after_sunrays, after_query)
+    };
+
+    // the sunray charging the last cell frees one as soon as the cells are full
+    assert_eq!(run(None), (1, 1));
+
+    // a sunray expected within the lead time is predicted from the schedule
+    let periodicity = PeriodicityConfig {
+        sunray_lead_ms: 1_000,
+        ..Default::default()
+    };
+    assert_eq!(run(Some(periodicity)), (1, 1));
+}
+
+#[test] // a prior worth more samples is moved less by the first intervals
+fn test_prior_pseudo_count() {
+    let estimate_after_samples = |pseudo_count: u32| {
+        let clock = Clock::manual(1_000_000);
+        let config = CiucConfig {
+            sunray_prior: Some(Prior {
+                mean_interval_ms: 1_000.0,
+                pseudo_count,
+            }),
+            ..Default::default()
+        };
+        let ai = CiucAI::with_clock(config, clock.clone());
+        let decisions = ai.decision_log();
+        let orchestrator = TestOrchestrator::started(60, ai).unwrap();
+        let explorer = MockExplorer::arrive(&orchestrator, 1).unwrap();
+        for _ in 0..4 {
+            orchestrator.send_sunray().unwrap();
+            clock.advance(2_000);
+        }
+        explorer.request_carbon().unwrap();
+        decisions.last().unwrap().estimate_sunray_ms
+    };
+
+    let weak = estimate_after_samples(1);
+    let strong = estimate_after_samples(50);
+    assert!(strong < weak, "pseudo_count 50: {strong}, pseudo_count 1: {weak}");
+    // 3 intervals averaged with 50 prior ones
+    assert!((strong - (50.0 * 1_000.0 + 3.0 * 2_000.0) / 53.0).abs() < 1e-6);
+}